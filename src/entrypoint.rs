@@ -3,10 +3,11 @@ use solana_program::{
     account_info::AccountInfo, 
     entrypoint, 
     entrypoint::ProgramResult, 
+    program_error::PrintProgramError,
     pubkey::Pubkey,
 };
 
-use crate::processor::Processor;
+use crate::{error::RoyaltyDistributorError, processor::Processor};
 
 entrypoint!(process_instruction);
 fn process_instruction(
@@ -14,5 +15,10 @@ fn process_instruction(
     accounts: &[AccountInfo],
     instruction_data: &[u8],
 ) -> ProgramResult {
-    Processor::process(program_id, accounts, instruction_data)
+    if let Err(error) = Processor::process(program_id, accounts, instruction_data) {
+        // Log the error message along with its code
+        error.print::<RoyaltyDistributorError>();
+        return Err(error);
+    }
+    Ok(())
 }
@@ -1,12 +1,171 @@
-use solana_program::program_error::ProgramError;
+// The `FromPrimitive` derive of num-derive 0.3, the version the Solana 1.6 toolchain
+// builds, implements the trait inside a named constant
+#![allow(unknown_lints, non_local_definitions)]
+
+use num_derive::FromPrimitive;
+use solana_program::{
+    decode_error::DecodeError,
+    msg,
+    program_error::{PrintProgramError, ProgramError},
+};
 use thiserror::Error;
 
-#[derive(Error, Debug, Copy, Clone)]
+/// Errors of the royalty distributor program, surfaced as `ProgramError::Custom(code)`
+///
+/// Codes are stable: new errors are appended with the next code, existing ones
+/// are never renumbered, so clients can map them to their own messages.
+#[derive(Error, Debug, Copy, Clone, PartialEq, FromPrimitive)]
 pub enum RoyaltyDistributorError {
     #[error("Invalid Instruction")]
-    InvalidInstruction,
+    InvalidInstruction = 0,
     #[error("Not Rent Exempt")]
-    NotRentExempt,
+    NotRentExempt = 1,
+    #[error("Invalid Member Pubkey")]
+    InvalidMemberPubkey = 2,
+    #[error("Duplicate Member")]
+    DuplicateMember = 3,
+    #[error("Member Has Zero Shares")]
+    ZeroShares = 4,
+    #[error("Shares Do Not Sum To 10000")]
+    InvalidSharesTotal = 5,
+    #[error("Invalid Authority")]
+    InvalidAuthority = 6,
+    #[error("Shared Account Mismatch")]
+    SharedAccountMismatch = 7,
+    #[error("Mint Mismatch")]
+    MintMismatch = 8,
+    #[error("Shared Account Not Owned By Authority")]
+    InvalidSharedAccountOwner = 9,
+    #[error("Arithmetic Overflow")]
+    ArithmeticOverflow = 10,
+    #[error("Invalid Remainder Member")]
+    InvalidRemainderMember = 11,
+    #[error("Not A Member")]
+    NotAMember = 12,
+    #[error("Distribution Not Allowed")]
+    DistributionNotAllowed = 13,
+    #[error("Invalid Cranker")]
+    InvalidCranker = 14,
+    #[error("Destination Not Owned By Member")]
+    DestinationOwnerMismatch = 15,
+    #[error("Destination Mint Mismatch")]
+    DestinationMintMismatch = 16,
+    #[error("Invalid Admin")]
+    InvalidAdmin = 17,
+    #[error("Member Count Mismatch")]
+    MemberCountMismatch = 18,
+    #[error("Invalid Approval Threshold")]
+    InvalidApprovalThreshold = 19,
+    #[error("Share Changes Governed By Members")]
+    GovernedByMembers = 20,
+    #[error("Share Changes Not Governed By Members")]
+    NotGovernedByMembers = 21,
+    #[error("Proposal Mismatch")]
+    ProposalMismatch = 22,
+    #[error("Proposal Expired")]
+    ProposalExpired = 23,
+    #[error("Proposal Already Executed")]
+    ProposalAlreadyExecuted = 24,
+    #[error("Proposal Already Approved")]
+    ProposalAlreadyApproved = 25,
+    #[error("Approval Threshold Not Reached")]
+    ApprovalThresholdNotReached = 26,
+    #[error("Invalid Pending Admin")]
+    InvalidPendingAdmin = 27,
+    #[error("Distributor Is Immutable")]
+    DistributorImmutable = 28,
+    #[error("Invalid Timelock")]
+    InvalidTimelock = 29,
+    #[error("Pending Shares Update")]
+    PendingSharesUpdate = 30,
+    #[error("No Pending Shares Update")]
+    NoPendingSharesUpdate = 31,
+    #[error("Shares Update Already In Effect")]
+    SharesUpdateInEffect = 32,
+    #[error("Shares Update Already Contested")]
+    SharesUpdateAlreadyContested = 33,
+    #[error("Mint Ledger Mismatch")]
+    MintLedgerMismatch = 34,
+    #[error("Mint Already Registered")]
+    MintAlreadyRegistered = 35,
+    #[error("Distributor Is Timelocked")]
+    DistributorTimelocked = 36,
+    #[error("Member Is A Distributor")]
+    MemberIsDistributor = 37,
+    #[error("Nested Distributor Mismatch")]
+    NestedDistributorMismatch = 38,
+    #[error("Nesting Would Allow A Cycle")]
+    NestingCycle = 39,
+    #[error("Nesting Too Deep")]
+    NestingTooDeep = 40,
+    #[error("Invalid Metadata")]
+    InvalidMetadata = 41,
+    #[error("Unverified Creator")]
+    UnverifiedCreator = 42,
+    #[error("Invalid Event")]
+    InvalidEvent = 43,
+    #[error("Invalid Receipt Retention")]
+    InvalidReceiptRetention = 44,
+    #[error("Receipt Mismatch")]
+    ReceiptMismatch = 45,
+    #[error("Receipt Still Retained")]
+    ReceiptRetained = 46,
+    #[error("State Account Not Owned By Program")]
+    InvalidStateAccountOwner = 47,
+    #[error("Proposal Account Not Owned By Program")]
+    InvalidProposalAccountOwner = 48,
+    #[error("Ledger Account Not Owned By Program")]
+    InvalidLedgerAccountOwner = 49,
+    #[error("Receipt Account Not Owned By Program")]
+    InvalidReceiptAccountOwner = 50,
+    #[error("Vault Not Owned By Program")]
+    InvalidVaultOwner = 51,
+    #[error("Shared Account Not A Token Account")]
+    SharedAccountNotTokenAccount = 52,
+    #[error("Destination Account Not A Token Account")]
+    DestinationNotTokenAccount = 53,
+    #[error("Invalid Token Program")]
+    InvalidTokenProgram = 54,
+    #[error("Invalid System Program")]
+    InvalidSystemProgram = 55,
+    #[error("Metadata Account Not Owned By Token Metadata Program")]
+    InvalidMetadataAccountOwner = 56,
+    #[error("Account Already Initialized")]
+    AlreadyInitialized = 57,
+    #[error("State Account Too Small")]
+    StateAccountTooSmall = 58,
+    #[error("Proposal Account Too Small")]
+    ProposalAccountTooSmall = 59,
+    #[error("Invalid New Shared Account Owner")]
+    InvalidNewSharedAccountOwner = 60,
+    #[error("Distributor Is Nested")]
+    DistributorNested = 61,
+    #[error("State Account Not Initialized")]
+    StateAccountNotInitialized = 62,
+    #[error("Invalid State Account Data")]
+    InvalidStateAccountData = 63,
+    #[error("Proposal Account Not Initialized")]
+    ProposalAccountNotInitialized = 64,
+    #[error("Invalid Proposal Account Data")]
+    InvalidProposalAccountData = 65,
+    #[error("Ledger Account Not Initialized")]
+    LedgerAccountNotInitialized = 66,
+    #[error("Invalid Ledger Account Data")]
+    InvalidLedgerAccountData = 67,
+    #[error("Receipt Account Not Initialized")]
+    ReceiptAccountNotInitialized = 68,
+    #[error("Invalid Receipt Account Data")]
+    InvalidReceiptAccountData = 69,
+    #[error("Missing Signer")]
+    MissingSigner = 70,
+    #[error("Not Enough Accounts")]
+    NotEnoughAccounts = 71,
+    #[error("Shared Account Has Delegate")]
+    SharedAccountDelegated = 72,
+    #[error("Invalid Shared Account Close Authority")]
+    InvalidSharedAccountCloseAuthority = 73,
+    #[error("Shared Account Balance Below Pending Amounts")]
+    SharedBalanceBelowPending = 74,
 }
 
 impl From<RoyaltyDistributorError> for ProgramError {
@@ -14,3 +173,22 @@ impl From<RoyaltyDistributorError> for ProgramError {
         ProgramError::Custom(err as u32)
     }
 }
+
+impl<T> DecodeError<T> for RoyaltyDistributorError {
+    fn type_of() -> &'static str {
+        "RoyaltyDistributorError"
+    }
+}
+
+impl PrintProgramError for RoyaltyDistributorError {
+    fn print<E>(&self)
+    where
+        E: 'static
+            + std::error::Error
+            + DecodeError<E>
+            + PrintProgramError
+            + num_traits::FromPrimitive,
+    {
+        msg!("Error: {}", self);
+    }
+}
@@ -0,0 +1,735 @@
+//! Events logged by the program, so indexers can rebuild payout history from transaction logs
+//!
+//! Each event is logged as `EVENT_PREFIX` followed by its base64 encoded data:
+//! the schema version, the event tag, then the event fields, little-endian,
+//! each vector preceded by its `u16` length.
+
+use std::convert::TryInto;
+
+use solana_program::{msg, program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{error::RoyaltyDistributorError::InvalidEvent, state::RoyaltyDistributor};
+
+/// Prefix of the log messages carrying events
+pub const EVENT_PREFIX: &str = "Event: ";
+
+/// Version of the event schema, bumped on any change to the encoding below
+pub const EVENT_VERSION: u8 = 1;
+
+/// Share table entry of a member, as logged
+#[derive(Clone, Debug, PartialEq)]
+pub struct MemberSnapshot {
+    pub pubkey: Pubkey,
+    pub shares: u16,
+    pub pending_shares: u16,
+    pub contested: bool,
+    pub is_distributor: bool,
+}
+
+/// Governance settings and share table of a distributor, as logged
+#[derive(Clone, Debug, PartialEq)]
+pub struct ConfigSnapshot {
+    pub admin: Pubkey,
+    pub pending_admin: Pubkey,
+    pub approval_threshold: u16,
+    pub timelock: i64,
+    // Time the pending shares take effect, 0 if none
+    pub pending_effective_at: i64,
+    pub members: Vec<MemberSnapshot>,
+}
+
+impl From<&RoyaltyDistributor> for ConfigSnapshot {
+    fn from(distributor: &RoyaltyDistributor) -> Self {
+        Self {
+            admin: distributor.admin,
+            pending_admin: distributor.pending_admin,
+            approval_threshold: distributor.approval_threshold,
+            timelock: distributor.timelock,
+            pending_effective_at: distributor.pending_effective_at,
+            members: distributor
+                .members
+                .iter()
+                .map(|member| MemberSnapshot {
+                    pubkey: member.pubkey,
+                    shares: member.shares,
+                    pending_shares: member.pending_shares,
+                    contested: member.contested,
+                    is_distributor: member.is_distributor,
+                })
+                .collect(),
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq)]
+pub enum Event {
+    /// A distributor was initialized
+    /// `mint` is the default pubkey for native distributors
+    Init {
+        distributor: Pubkey,
+        mint: Pubkey,
+        config: ConfigSnapshot,
+        slot: u64,
+    },
+
+    /// The balance of a shared account was paid out to every member
+    /// `amounts` are in member order, `remainder` is what stays in the shared account
+    Distribution {
+        distributor: Pubkey,
+        mint: Pubkey,
+        balance: u64,
+        amounts: Vec<u64>,
+        remainder: u64,
+        slot: u64,
+    },
+
+    /// A single member was paid out
+    Claim {
+        distributor: Pubkey,
+        mint: Pubkey,
+        member: Pubkey,
+        amount: u64,
+        slot: u64,
+    },
+
+    /// The governance settings or the share table of a distributor changed
+    ConfigChange {
+        distributor: Pubkey,
+        config: ConfigSnapshot,
+        slot: u64,
+    },
+
+    /// A share table proposal was made or approved
+    /// `approvals` are the members who approved so far, the proposer first
+    ProposalChange {
+        distributor: Pubkey,
+        proposal: Pubkey,
+        proposer: Pubkey,
+        expires_at: i64,
+        shares: Vec<u16>,
+        approvals: Vec<Pubkey>,
+        slot: u64,
+    },
+
+    /// A mint was registered with a distributor, along with its shared account
+    MintRegistration {
+        distributor: Pubkey,
+        mint: Pubkey,
+        shared_account: Pubkey,
+        slot: u64,
+    },
+
+    /// A distributor was closed after a final distribution
+    /// `new_shared_account_owner` received its shared accounts, unused by native distributors
+    Close {
+        distributor: Pubkey,
+        new_shared_account_owner: Pubkey,
+        slot: u64,
+    },
+}
+
+impl Event {
+    /// Logs the event
+    pub fn emit(&self) {
+        msg!("{}{}", EVENT_PREFIX, encode_base64(&self.pack()));
+    }
+
+    /// Decodes the event carried by a log message, with or without
+    /// the `Program log: ` prefix added by the runtime
+    pub fn decode(log: &str) -> Result<Self, ProgramError> {
+        let log = log.strip_prefix("Program log: ").unwrap_or(log);
+        let data = log.strip_prefix(EVENT_PREFIX).ok_or(InvalidEvent)?;
+        Self::unpack(&decode_base64(data)?)
+    }
+
+    pub fn pack(&self) -> Vec<u8> {
+        let mut buf = vec![EVENT_VERSION];
+        match self {
+            Self::Init {
+                distributor,
+                mint,
+                config,
+                slot,
+            } => {
+                buf.push(0);
+                buf.extend_from_slice(distributor.as_ref());
+                buf.extend_from_slice(mint.as_ref());
+                pack_config(config, &mut buf);
+                buf.extend_from_slice(&slot.to_le_bytes());
+            }
+            Self::Distribution {
+                distributor,
+                mint,
+                balance,
+                amounts,
+                remainder,
+                slot,
+            } => {
+                buf.push(1);
+                buf.extend_from_slice(distributor.as_ref());
+                buf.extend_from_slice(mint.as_ref());
+                buf.extend_from_slice(&balance.to_le_bytes());
+                buf.extend_from_slice(&(amounts.len() as u16).to_le_bytes());
+                for amount in amounts.iter() {
+                    buf.extend_from_slice(&amount.to_le_bytes());
+                }
+                buf.extend_from_slice(&remainder.to_le_bytes());
+                buf.extend_from_slice(&slot.to_le_bytes());
+            }
+            Self::Claim {
+                distributor,
+                mint,
+                member,
+                amount,
+                slot,
+            } => {
+                buf.push(2);
+                buf.extend_from_slice(distributor.as_ref());
+                buf.extend_from_slice(mint.as_ref());
+                buf.extend_from_slice(member.as_ref());
+                buf.extend_from_slice(&amount.to_le_bytes());
+                buf.extend_from_slice(&slot.to_le_bytes());
+            }
+            Self::ConfigChange {
+                distributor,
+                config,
+                slot,
+            } => {
+                buf.push(3);
+                buf.extend_from_slice(distributor.as_ref());
+                pack_config(config, &mut buf);
+                buf.extend_from_slice(&slot.to_le_bytes());
+            }
+            Self::ProposalChange {
+                distributor,
+                proposal,
+                proposer,
+                expires_at,
+                shares,
+                approvals,
+                slot,
+            } => {
+                buf.push(4);
+                buf.extend_from_slice(distributor.as_ref());
+                buf.extend_from_slice(proposal.as_ref());
+                buf.extend_from_slice(proposer.as_ref());
+                buf.extend_from_slice(&expires_at.to_le_bytes());
+                buf.extend_from_slice(&(shares.len() as u16).to_le_bytes());
+                for share in shares.iter() {
+                    buf.extend_from_slice(&share.to_le_bytes());
+                }
+                buf.extend_from_slice(&(approvals.len() as u16).to_le_bytes());
+                for approval in approvals.iter() {
+                    buf.extend_from_slice(approval.as_ref());
+                }
+                buf.extend_from_slice(&slot.to_le_bytes());
+            }
+            Self::MintRegistration {
+                distributor,
+                mint,
+                shared_account,
+                slot,
+            } => {
+                buf.push(5);
+                buf.extend_from_slice(distributor.as_ref());
+                buf.extend_from_slice(mint.as_ref());
+                buf.extend_from_slice(shared_account.as_ref());
+                buf.extend_from_slice(&slot.to_le_bytes());
+            }
+            Self::Close {
+                distributor,
+                new_shared_account_owner,
+                slot,
+            } => {
+                buf.push(6);
+                buf.extend_from_slice(distributor.as_ref());
+                buf.extend_from_slice(new_shared_account_owner.as_ref());
+                buf.extend_from_slice(&slot.to_le_bytes());
+            }
+        }
+        buf
+    }
+
+    pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
+        let (version, rest) = input.split_first().ok_or(InvalidEvent)?;
+        if *version != EVENT_VERSION {
+            return Err(InvalidEvent.into());
+        }
+
+        let (tag, rest) = rest.split_first().ok_or(InvalidEvent)?;
+        let (event, rest) = match tag {
+            0 => {
+                let (distributor, rest) = unpack_pubkey(rest)?;
+                let (mint, rest) = unpack_pubkey(rest)?;
+                let (config, rest) = unpack_config(rest)?;
+                let (slot, rest) = unpack_u64(rest)?;
+                (
+                    Self::Init {
+                        distributor,
+                        mint,
+                        config,
+                        slot,
+                    },
+                    rest,
+                )
+            }
+            1 => {
+                let (distributor, rest) = unpack_pubkey(rest)?;
+                let (mint, rest) = unpack_pubkey(rest)?;
+                let (balance, rest) = unpack_u64(rest)?;
+                let (amount_count, mut rest) = unpack_u16(rest)?;
+                let mut amounts = Vec::with_capacity(amount_count as usize);
+                for _ in 0..amount_count {
+                    let (amount, next) = unpack_u64(rest)?;
+                    amounts.push(amount);
+                    rest = next;
+                }
+                let (remainder, rest) = unpack_u64(rest)?;
+                let (slot, rest) = unpack_u64(rest)?;
+                (
+                    Self::Distribution {
+                        distributor,
+                        mint,
+                        balance,
+                        amounts,
+                        remainder,
+                        slot,
+                    },
+                    rest,
+                )
+            }
+            2 => {
+                let (distributor, rest) = unpack_pubkey(rest)?;
+                let (mint, rest) = unpack_pubkey(rest)?;
+                let (member, rest) = unpack_pubkey(rest)?;
+                let (amount, rest) = unpack_u64(rest)?;
+                let (slot, rest) = unpack_u64(rest)?;
+                (
+                    Self::Claim {
+                        distributor,
+                        mint,
+                        member,
+                        amount,
+                        slot,
+                    },
+                    rest,
+                )
+            }
+            3 => {
+                let (distributor, rest) = unpack_pubkey(rest)?;
+                let (config, rest) = unpack_config(rest)?;
+                let (slot, rest) = unpack_u64(rest)?;
+                (
+                    Self::ConfigChange {
+                        distributor,
+                        config,
+                        slot,
+                    },
+                    rest,
+                )
+            }
+            4 => {
+                let (distributor, rest) = unpack_pubkey(rest)?;
+                let (proposal, rest) = unpack_pubkey(rest)?;
+                let (proposer, rest) = unpack_pubkey(rest)?;
+                let (expires_at, rest) = unpack_u64(rest)?;
+                let (share_count, mut rest) = unpack_u16(rest)?;
+                let mut shares = Vec::with_capacity(share_count as usize);
+                for _ in 0..share_count {
+                    let (share, next) = unpack_u16(rest)?;
+                    shares.push(share);
+                    rest = next;
+                }
+                let (approval_count, mut rest) = unpack_u16(rest)?;
+                let mut approvals = Vec::with_capacity(approval_count as usize);
+                for _ in 0..approval_count {
+                    let (approval, next) = unpack_pubkey(rest)?;
+                    approvals.push(approval);
+                    rest = next;
+                }
+                let (slot, rest) = unpack_u64(rest)?;
+                (
+                    Self::ProposalChange {
+                        distributor,
+                        proposal,
+                        proposer,
+                        expires_at: expires_at as i64,
+                        shares,
+                        approvals,
+                        slot,
+                    },
+                    rest,
+                )
+            }
+            5 => {
+                let (distributor, rest) = unpack_pubkey(rest)?;
+                let (mint, rest) = unpack_pubkey(rest)?;
+                let (shared_account, rest) = unpack_pubkey(rest)?;
+                let (slot, rest) = unpack_u64(rest)?;
+                (
+                    Self::MintRegistration {
+                        distributor,
+                        mint,
+                        shared_account,
+                        slot,
+                    },
+                    rest,
+                )
+            }
+            6 => {
+                let (distributor, rest) = unpack_pubkey(rest)?;
+                let (new_shared_account_owner, rest) = unpack_pubkey(rest)?;
+                let (slot, rest) = unpack_u64(rest)?;
+                (
+                    Self::Close {
+                        distributor,
+                        new_shared_account_owner,
+                        slot,
+                    },
+                    rest,
+                )
+            }
+            _ => return Err(InvalidEvent.into()),
+        };
+
+        if !rest.is_empty() {
+            return Err(InvalidEvent.into());
+        }
+        Ok(event)
+    }
+}
+
+fn pack_config(config: &ConfigSnapshot, buf: &mut Vec<u8>) {
+    buf.extend_from_slice(config.admin.as_ref());
+    buf.extend_from_slice(config.pending_admin.as_ref());
+    buf.extend_from_slice(&config.approval_threshold.to_le_bytes());
+    buf.extend_from_slice(&config.timelock.to_le_bytes());
+    buf.extend_from_slice(&config.pending_effective_at.to_le_bytes());
+    buf.extend_from_slice(&(config.members.len() as u16).to_le_bytes());
+    for member in config.members.iter() {
+        buf.extend_from_slice(member.pubkey.as_ref());
+        buf.extend_from_slice(&member.shares.to_le_bytes());
+        buf.extend_from_slice(&member.pending_shares.to_le_bytes());
+        buf.push(member.contested as u8);
+        buf.push(member.is_distributor as u8);
+    }
+}
+
+fn unpack_config(input: &[u8]) -> Result<(ConfigSnapshot, &[u8]), ProgramError> {
+    let (admin, rest) = unpack_pubkey(input)?;
+    let (pending_admin, rest) = unpack_pubkey(rest)?;
+    let (approval_threshold, rest) = unpack_u16(rest)?;
+    let (timelock, rest) = unpack_u64(rest)?;
+    let (pending_effective_at, rest) = unpack_u64(rest)?;
+    let (member_count, mut rest) = unpack_u16(rest)?;
+
+    let mut members = Vec::with_capacity(member_count as usize);
+    for _ in 0..member_count {
+        let (pubkey, next) = unpack_pubkey(rest)?;
+        let (shares, next) = unpack_u16(next)?;
+        let (pending_shares, next) = unpack_u16(next)?;
+        let (contested, next) = unpack_bool(next)?;
+        let (is_distributor, next) = unpack_bool(next)?;
+        members.push(MemberSnapshot {
+            pubkey,
+            shares,
+            pending_shares,
+            contested,
+            is_distributor,
+        });
+        rest = next;
+    }
+
+    Ok((
+        ConfigSnapshot {
+            admin,
+            pending_admin,
+            approval_threshold,
+            timelock: timelock as i64,
+            pending_effective_at: pending_effective_at as i64,
+            members,
+        },
+        rest,
+    ))
+}
+
+fn split(input: &[u8], len: usize) -> Result<(&[u8], &[u8]), ProgramError> {
+    if input.len() < len {
+        return Err(InvalidEvent.into());
+    }
+    Ok(input.split_at(len))
+}
+
+fn unpack_bool(input: &[u8]) -> Result<(bool, &[u8]), ProgramError> {
+    let (value, rest) = input.split_first().ok_or(InvalidEvent)?;
+    match value {
+        0 => Ok((false, rest)),
+        1 => Ok((true, rest)),
+        _ => Err(InvalidEvent.into()),
+    }
+}
+
+fn unpack_pubkey(input: &[u8]) -> Result<(Pubkey, &[u8]), ProgramError> {
+    let (pubkey, rest) = split(input, 32)?;
+    Ok((Pubkey::new(pubkey), rest))
+}
+
+fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+    let (value, rest) = split(input, 2)?;
+    let value = value.try_into().map_err(|_| InvalidEvent)?;
+    Ok((u16::from_le_bytes(value), rest))
+}
+
+fn unpack_u64(input: &[u8]) -> Result<(u64, &[u8]), ProgramError> {
+    let (value, rest) = split(input, 8)?;
+    let value = value.try_into().map_err(|_| InvalidEvent)?;
+    Ok((u64::from_le_bytes(value), rest))
+}
+
+const BASE64_ALPHABET: &[u8; 64] =
+    b"ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz0123456789+/";
+
+/// Standard base64 with padding, as expected by usual log tooling
+fn encode_base64(input: &[u8]) -> String {
+    let mut output = String::with_capacity((input.len() + 2) / 3 * 4);
+    for chunk in input.chunks(3) {
+        let bytes = [
+            chunk[0],
+            chunk.get(1).copied().unwrap_or(0),
+            chunk.get(2).copied().unwrap_or(0),
+        ];
+        let bits = u32::from_be_bytes([0, bytes[0], bytes[1], bytes[2]]);
+        for i in 0..4 {
+            if i <= chunk.len() {
+                let index = (bits >> (18 - 6 * i)) & 0x3f;
+                output.push(BASE64_ALPHABET[index as usize] as char);
+            } else {
+                output.push('=');
+            }
+        }
+    }
+    output
+}
+
+fn decode_base64(input: &str) -> Result<Vec<u8>, ProgramError> {
+    let input = input.trim_end().as_bytes();
+    if input.len() % 4 != 0 {
+        return Err(InvalidEvent.into());
+    }
+
+    let mut output = Vec::with_capacity(input.len() / 4 * 3);
+    for (chunk_index, chunk) in input.chunks_exact(4).enumerate() {
+        let is_last = chunk_index == input.len() / 4 - 1;
+        let padding = chunk.iter().rev().take_while(|&&c| c == b'=').count();
+        if padding > 2 || (padding > 0 && !is_last) {
+            return Err(InvalidEvent.into());
+        }
+
+        let mut bits = 0u32;
+        for &c in chunk[..4 - padding].iter() {
+            let index = BASE64_ALPHABET
+                .iter()
+                .position(|&a| a == c)
+                .ok_or(InvalidEvent)?;
+            bits = bits << 6 | index as u32;
+        }
+        bits <<= 6 * padding as u32;
+
+        let bytes = bits.to_be_bytes();
+        output.extend_from_slice(&bytes[1..4 - padding]);
+    }
+    Ok(output)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> ConfigSnapshot {
+        ConfigSnapshot {
+            admin: Pubkey::new_unique(),
+            pending_admin: Pubkey::new_unique(),
+            approval_threshold: 5001,
+            timelock: 86400,
+            pending_effective_at: -1,
+            members: vec![
+                MemberSnapshot {
+                    pubkey: Pubkey::new_unique(),
+                    shares: 7500,
+                    pending_shares: 5000,
+                    contested: true,
+                    is_distributor: false,
+                },
+                MemberSnapshot {
+                    pubkey: Pubkey::new_unique(),
+                    shares: 2500,
+                    pending_shares: 5000,
+                    contested: false,
+                    is_distributor: true,
+                },
+            ],
+        }
+    }
+
+    fn events() -> Vec<Event> {
+        vec![
+            Event::Init {
+                distributor: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                config: config(),
+                slot: 1,
+            },
+            Event::Distribution {
+                distributor: Pubkey::new_unique(),
+                mint: Pubkey::default(),
+                balance: u64::MAX,
+                amounts: vec![u64::MAX - 1, 0, 1],
+                remainder: 0,
+                slot: 2,
+            },
+            Event::Claim {
+                distributor: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                member: Pubkey::new_unique(),
+                amount: 42,
+                slot: 3,
+            },
+            Event::ConfigChange {
+                distributor: Pubkey::new_unique(),
+                config: ConfigSnapshot {
+                    members: vec![],
+                    ..config()
+                },
+                slot: 4,
+            },
+            Event::ProposalChange {
+                distributor: Pubkey::new_unique(),
+                proposal: Pubkey::new_unique(),
+                proposer: Pubkey::new_unique(),
+                expires_at: i64::MIN,
+                shares: vec![6000, 4000],
+                approvals: vec![Pubkey::new_unique(), Pubkey::new_unique()],
+                slot: 5,
+            },
+            Event::MintRegistration {
+                distributor: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                shared_account: Pubkey::new_unique(),
+                slot: u64::MAX,
+            },
+            Event::Close {
+                distributor: Pubkey::new_unique(),
+                new_shared_account_owner: Pubkey::new_unique(),
+                slot: 6,
+            },
+        ]
+    }
+
+    fn log(event: &Event) -> String {
+        format!("{}{}", EVENT_PREFIX, encode_base64(&event.pack()))
+    }
+
+    #[test]
+    fn test_round_trip() {
+        for event in events() {
+            assert_eq!(Event::unpack(&event.pack()).unwrap(), event);
+            assert_eq!(Event::decode(&log(&event)).unwrap(), event);
+            assert_eq!(
+                Event::decode(&format!("Program log: {}", log(&event))).unwrap(),
+                event
+            );
+        }
+    }
+
+    #[test]
+    fn test_round_trip_every_padding() {
+        // Data lengths of every remainder modulo 3 go through the base64 padding cases
+        let mut padding_lens = Vec::new();
+        for amount_count in 0..3 {
+            let event = Event::Distribution {
+                distributor: Pubkey::new_unique(),
+                mint: Pubkey::new_unique(),
+                balance: 3,
+                amounts: vec![1; amount_count],
+                remainder: 0,
+                slot: 0,
+            };
+            let log = log(&event);
+            padding_lens.push(log.len() - log.trim_end_matches('=').len());
+            assert_eq!(Event::decode(&log).unwrap(), event);
+        }
+        padding_lens.sort_unstable();
+        assert_eq!(padding_lens, vec![0, 1, 2]);
+    }
+
+    #[test]
+    fn test_base64() {
+        // Test vectors of RFC 4648
+        let vectors = [
+            ("", ""),
+            ("f", "Zg=="),
+            ("fo", "Zm8="),
+            ("foo", "Zm9v"),
+            ("foob", "Zm9vYg=="),
+            ("fooba", "Zm9vYmE="),
+            ("foobar", "Zm9vYmFy"),
+        ];
+        for (data, encoded) in vectors.iter() {
+            assert_eq!(encode_base64(data.as_bytes()), *encoded);
+            assert_eq!(decode_base64(encoded).unwrap(), data.as_bytes());
+        }
+
+        let bytes: Vec<u8> = (0..=255).collect();
+        assert_eq!(decode_base64(&encode_base64(&bytes)).unwrap(), bytes);
+    }
+
+    #[test]
+    fn test_decode_invalid_base64() {
+        for encoded in ["Zm9", "Zm9vY", "Z===", "Zg==Zm9v", "Zm9*", "Zm=v"].iter() {
+            assert_eq!(decode_base64(encoded), Err(InvalidEvent.into()));
+        }
+    }
+
+    #[test]
+    fn test_decode_invalid_event() {
+        let event = events().remove(2);
+
+        // Not an event log
+        assert_eq!(
+            Event::decode("Program log: Instruction: Withdraw"),
+            Err(InvalidEvent.into())
+        );
+
+        // Unknown schema version
+        let mut data = event.pack();
+        data[0] = EVENT_VERSION + 1;
+        assert_eq!(Event::unpack(&data), Err(InvalidEvent.into()));
+
+        // Unknown tag
+        let mut data = event.pack();
+        data[1] = 255;
+        assert_eq!(Event::unpack(&data), Err(InvalidEvent.into()));
+
+        // Truncated or trailing data
+        let data = event.pack();
+        assert_eq!(
+            Event::unpack(&data[..data.len() - 1]),
+            Err(InvalidEvent.into())
+        );
+        let mut data = event.pack();
+        data.push(0);
+        assert_eq!(Event::unpack(&data), Err(InvalidEvent.into()));
+        assert_eq!(Event::unpack(&[]), Err(InvalidEvent.into()));
+
+        // Flags other than 0 or 1
+        let event = Event::ConfigChange {
+            distributor: Pubkey::new_unique(),
+            config: config(),
+            slot: 0,
+        };
+        let mut data = event.pack();
+        let flag_index = data.len() - 8 - 1;
+        data[flag_index] = 2;
+        assert_eq!(Event::unpack(&data), Err(InvalidEvent.into()));
+    }
+}
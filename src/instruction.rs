@@ -1,50 +1,87 @@
-// use arrayref::{array_ref, array_refs};
-use solana_program::program_error::ProgramError;
+use arrayref::{array_ref, array_refs};
+use solana_program::{program_error::ProgramError, program_pack::Pack, pubkey::Pubkey};
 
-use crate::error::RoyaltyDistributorError::InvalidInstruction;
+use crate::{
+    error::RoyaltyDistributorError::InvalidInstruction,
+    state::{DistributionPolicy, Member, ReceiptPolicy, RemainderPolicy},
+};
+
+/// Configuration of a royalty distributor, set at initialization
+pub struct DistributorConfig {
+    pub remainder_policy: RemainderPolicy,
+    pub distribution_policy: DistributionPolicy,
+    pub admin: Pubkey,
+    pub approval_threshold: u16,
+    pub timelock: i64,
+    pub is_native: bool,
+    pub receipt_policy: ReceiptPolicy,
+}
 
 pub enum RoyaltyDistributorInstruction {
     /// Initializes the royalty distributor by:
     /// * Creating and populating a royalty distributor state account
     /// * Transferring ownership of the shared account to the PDA
     ///
+    /// The PDA is derived from the state account key (see `state::find_authority_address`),
+    /// so every distributor has its own authority over its shared account
+    ///
+    /// Native distributors share SOL instead: the PDA itself is created as a vault owned by
+    /// this program, royalties are deposited into it as lamports, and it stays rent exempt.
+    /// In every other instruction, the vault is passed as both the shared account and the
+    /// PDA account, destination accounts are the member wallets themselves, and other
+    /// accounts, including the token program account, are unchanged.
+    ///
     /// Accounts expected:
     /// 0. `[signer]`
     ///    * The account of the initializer
     ///    * Transfering ownership of shared account requires signature of initializer
+    ///    * Should be writable for native distributors, as it funds the vault
     ///
     /// 1. `[writable]`
     ///    * Shared account: token account that holds tokens to be shared between members
     ///    * Should be created prior to this instruction and owned by the initializer
     ///    * Should be writable because its ownership will be transfered to the PDA
+    ///    * Should have no delegate, and no close authority other than the PDA
+    ///    * For native distributors: the PDA, created by this instruction as the vault
     ///
     /// 2. `[writable]`
     ///    * State account
-    ///    * Stores data about the royalty distributor: member public keys, member shares
+    ///    * Stores data about the royalty distributor: shared account, mint, admin, member public keys, member shares
     ///
     /// 3. `[]` The rent sysvar
     ///
-    /// 4. `[]` The token program account
-
-    /// NOTES: This is a proof of concept that supports only 2 members
+    /// 4. `[]` The token program account, or the system program account for native distributors
+    ///
+    /// Instruction data (after the tag):
+    /// * Remainder policy: `u8` tag (0: carry forward, 1: largest remainder, 2: member),
+    ///   `u16` little-endian member index (only used by the member policy)
+    /// * Distribution policy: `u8` tag (0: permissionless, 1: members only, 2: cranker),
+    ///   32 bytes cranker public key (only used by the cranker policy)
+    /// * 32 bytes admin public key, allowed to update shares (default public key for none)
+    /// * `u16` little-endian approval threshold: shares, in basis points, that should approve
+    ///   a proposal to change the share table (0 to let the admin change it instead)
+    /// * `i64` little-endian timelock: delay, in seconds, before a share table change
+    ///   takes effect (0 for none)
+    /// * `u8` native flag (0: SPL tokens, 1: native SOL)
+    /// * Receipt policy: `u8` tag (0: disabled, 1: retain), `i64` little-endian retention
+    ///   period, in seconds, before a receipt may be closed (only used by the retain policy)
+    /// * `u16` little-endian member count
+    /// * For each member: 32 bytes public key, `u16` little-endian shares in basis points
     ///
+    /// The state account should be at least `RoyaltyDistributor::get_packed_len(member count)` bytes
     InitRoyaltyDistributor {
-        member_1_shares: u16,
-        member_2_shares: u16,
-        member_3_shares: u16,
-        member_4_shares: u16,
-        member_5_shares: u16,
-        member_6_shares: u16,
-        member_7_shares: u16,
-        member_8_shares: u16,
+        config: DistributorConfig,
+        members: Vec<Member>,
     },
 
     /// Withdraw instruction
     /// Withdraw shares from the shared account
+    /// Allocates the shared account balance and transfers the pending amount of every member
     ///
     /// Accounts expected:
     /// 0. `[signer]`
-    ///    * Account of the member executing the withdraw
+    ///    * Account executing the withdraw
+    ///    * Should be allowed by the distribution policy: anyone, a member or the cranker
     ///
     /// 1. `[writable]`
     ///    * State account
@@ -52,34 +89,767 @@ pub enum RoyaltyDistributorInstruction {
     ///
     /// 2. `[writable]`
     ///    * Shared account: token account that holds tokens to be shared between members
+    ///    * Should be the shared account recorded in state, with the recorded mint, owned by the PDA
+    ///    * To settle a registered mint: the shared account recorded in its ledger
     ///
     /// 3. `[]` The token program account
     ///
     /// 4. `[]` The PDA account
-    Withdraw {},
+    ///    * Derived from the state account key and the bump seed stored in state
+    ///
+    /// 5. `[writable]` Only to settle a registered mint
+    ///    * Ledger account of the mint (see `state::find_mint_ledger_address`)
+    ///
+    /// 5 or 6. `[writable]` ... `[writable]`
+    ///    * Destination token accounts, one per member in member order
+    ///    * Each should hold the mint to settle and be owned by its member
+    ///      (the member's associated token account, for instance),
+    ///      or be the member wallet for native distributors
+    ///    * For a member flagged as a nested distributor, the shared account recorded by
+    ///      that distributor instead: in its state account for the distributor mint,
+    ///      in its ledger account of the mint for a registered mint
+    ///
+    /// Following the destination accounts, `[]` ... `[]`
+    ///    * For each member flagged as a nested distributor, in member order: its state account,
+    ///      or its ledger account of the mint to settle for a registered mint
+    ///
+    /// Only when the receipt policy of the distributor is to retain receipts, after the
+    /// nested distributor accounts:
+    ///
+    /// * `[writable]` Receipt account of the round: the PDA derived from the state account
+    ///   key and the receipt count stored in state (see `state::find_receipt_address`),
+    ///   created by this instruction and funded by account 0, which should be writable
+    ///
+    /// * `[]` The system program account
+    ///
+    /// Instruction data (after the tag), optional:
+    /// * 32 bytes mint to settle, the distributor mint if omitted
+    Withdraw { mint: Option<Pubkey> },
+
+    /// Claim instruction
+    /// Transfer the outstanding entitlement of the calling member from the shared account
+    /// Not available to nested distributors, paid out by Withdraw or DistributeTree only
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Account of the member claiming
+    ///
+    /// 1. `[writable]`
+    ///    * State account
+    ///
+    /// 2. `[writable]`
+    ///    * Shared account: token account that holds tokens to be shared between members
+    ///    * Should be the shared account recorded in state, with the recorded mint, owned by the PDA
+    ///    * To settle a registered mint: the shared account recorded in its ledger
+    ///
+    /// 3. `[writable]`
+    ///    * Destination token account of the member
+    ///    * Should hold the mint to settle and be owned by the member,
+    ///      or be the member wallet for native distributors
+    ///
+    /// 4. `[]` The token program account
+    ///
+    /// 5. `[]` The PDA account
+    ///    * Derived from the state account key and the bump seed stored in state
+    ///
+    /// 6. `[writable]` Only to settle a registered mint
+    ///    * Ledger account of the mint (see `state::find_mint_ledger_address`)
+    ///
+    /// Instruction data (after the tag), optional:
+    /// * 32 bytes mint to settle, the distributor mint if omitted
+    Claim { mint: Option<Pubkey> },
+
+    /// UpdateShares instruction
+    /// Rewrite the share table, after allocating the shared account balance under the old one
+    /// With a timelock, the new share table is pending until it takes effect: it applies to
+    /// the first allocation after the timelock elapses, unless members contest it first
+    /// Not available when share changes are governed by members
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Admin account recorded in state
+    ///
+    /// 1. `[writable]`
+    ///    * State account
+    ///
+    /// 2. `[]`
+    ///    * Shared account: token account that holds tokens to be shared between members
+    ///    * Should be the shared account recorded in state, with the recorded mint, owned by the PDA
+    ///
+    /// 3. `[writable]`, `[]` ... `[writable]`, `[]`
+    ///    * For each registered mint: its ledger account and its shared account
+    ///
+    /// Instruction data (after the tag):
+    /// * `u16` little-endian member count, which should match the current member count
+    /// * For each member, in member order: `u16` little-endian shares in basis points
+    UpdateShares { shares: Vec<u16> },
+
+    /// AddMember instruction
+    /// Append a member and rewrite the share table, after allocating the shared account
+    /// balance under the old one
+    /// Not available when share changes are governed by members or while a share table is pending
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Admin account recorded in state
+    ///
+    /// 1. `[writable]`
+    ///    * State account
+    ///    * Should be large enough to hold one more member
+    ///
+    /// 2. `[]`
+    ///    * Shared account: token account that holds tokens to be shared between members
+    ///    * Should be the shared account recorded in state, with the recorded mint, owned by the PDA
+    ///
+    /// 3. `[writable]`, `[]` ... `[writable]`, `[]`
+    ///    * For each registered mint: its ledger account and its shared account
+    ///    * Each ledger account should be large enough to hold one more member
+    ///
+    /// Instruction data (after the tag):
+    /// * 32 bytes public key of the new member
+    /// * `u16` little-endian member count, which should be the current member count plus one
+    /// * For each member, in member order and ending with the new member:
+    ///   `u16` little-endian shares in basis points
+    AddMember { pubkey: Pubkey, shares: Vec<u16> },
+
+    /// RemoveMember instruction
+    /// Remove a member and rewrite the share table, after allocating the shared account
+    /// balance under the old one and transferring the pending amount of the removed member
+    /// Not available when share changes are governed by members or while a share table is pending
+    /// Removing the member receiving the remainder switches the remainder policy to carry forward
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Admin account recorded in state
+    ///
+    /// 1. `[writable]`
+    ///    * State account
+    ///
+    /// 2. `[writable]`
+    ///    * Shared account: token account that holds tokens to be shared between members
+    ///    * Should be the shared account recorded in state, with the recorded mint, owned by the PDA
+    ///
+    /// 3. `[writable]`
+    ///    * Destination token account of the removed member
+    ///    * Should hold the recorded mint and be owned by the removed member,
+    ///      or be the member wallet for native distributors
+    ///    * For a nested distributor: the shared account recorded by that distributor
+    ///
+    /// 4. `[]` The token program account
+    ///
+    /// 5. `[]` The PDA account
+    ///    * Derived from the state account key and the bump seed stored in state
+    ///
+    /// 6. `[writable]` Only when the removed member is a nested distributor
+    ///    * State account of the removed member, whose nested flag is cleared
+    ///
+    /// Following account 6, `[]` ... `[]` Only when the removed member is a nested distributor
+    ///    * State account of each other nested distributor among the members, in member order,
+    ///      from which the nesting depth of this distributor is recomputed
+    ///
+    /// Following account 5 or the nested distributor accounts, `[writable]` ... `[writable]`
+    ///    * For each registered mint: its ledger account, its shared account and
+    ///      the destination token account of the removed member for that mint,
+    ///      then, for a nested distributor, its ledger account of that mint
+    ///
+    /// Instruction data (after the tag):
+    /// * `u16` little-endian index of the member to remove
+    /// * `u16` little-endian member count, which should be the current member count minus one
+    /// * For each remaining member, in member order: `u16` little-endian shares in basis points
+    RemoveMember { member_index: u16, shares: Vec<u16> },
+
+    /// TransferMembership instruction
+    /// Reassign the slot of a member, along with its shares and pending amount, to a new pubkey
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Account of the current member
+    ///
+    /// 1. `[writable]`
+    ///    * State account
+    ///
+    /// 2. `[]` or `[signer]`
+    ///    * Account of the new member
+    ///    * Should sign when the instruction requires the new member signature
+    ///
+    /// Instruction data (after the tag):
+    /// * `u8` 1 to require the new member signature, 0 otherwise
+    TransferMembership { require_new_member_signature: bool },
+
+    /// ProposeShares instruction
+    /// Propose a new share table, approved by the proposer
+    /// Only available when share changes are governed by members
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Account of the member making the proposal
+    ///
+    /// 1. `[]`
+    ///    * State account
+    ///
+    /// 2. `[writable]`
+    ///    * Proposal account
+    ///    * Should be created prior to this instruction, owned by this program, rent exempt
+    ///      and at least `Proposal::get_packed_len(member count)` bytes
+    ///
+    /// 3. `[]` The rent sysvar
+    ///
+    /// Instruction data (after the tag):
+    /// * `i64` little-endian unix timestamp after which the proposal expires
+    /// * `u16` little-endian member count, which should match the current member count
+    /// * For each member, in member order: `u16` little-endian shares in basis points
+    ProposeShares { expires_at: i64, shares: Vec<u16> },
+
+    /// ApproveProposal instruction
+    /// Approve a proposal with the shares of the calling member
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Account of the member approving
+    ///
+    /// 1. `[]`
+    ///    * State account
+    ///
+    /// 2. `[writable]`
+    ///    * Proposal account
+    ApproveProposal {},
+
+    /// ExecuteProposal instruction
+    /// Rewrite the share table with a proposal approved by members holding at least the
+    /// approval threshold, weighted by their current shares, after allocating the shared
+    /// account balance under the old one
+    /// With a timelock, the new share table is pending until it takes effect, as with UpdateShares
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]`
+    ///    * State account
+    ///
+    /// 1. `[writable]`
+    ///    * Proposal account
+    ///
+    /// 2. `[]`
+    ///    * Shared account: token account that holds tokens to be shared between members
+    ///    * Should be the shared account recorded in state, with the recorded mint, owned by the PDA
+    ///
+    /// 3. `[writable]`, `[]` ... `[writable]`, `[]`
+    ///    * For each registered mint: its ledger account and its shared account
+    ExecuteProposal {},
+
+    /// ProposeAdmin instruction
+    /// Propose a new admin, which becomes admin once it accepts
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Admin account recorded in state
+    ///
+    /// 1. `[writable]`
+    ///    * State account
+    ///
+    /// Instruction data (after the tag):
+    /// * 32 bytes public key of the proposed admin (default public key to cancel a proposal)
+    ProposeAdmin { pending_admin: Pubkey },
+
+    /// AcceptAdmin instruction
+    /// Become admin, as proposed by the current admin
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Pending admin account recorded in state
+    ///
+    /// 1. `[writable]`
+    ///    * State account
+    AcceptAdmin {},
+
+    /// RenounceAdmin instruction
+    /// Remove the admin and any member governance, so the share table can never change again
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Admin account recorded in state
+    ///
+    /// 1. `[writable]`
+    ///    * State account
+    ///
+    /// 2. `[signer]` ... `[signer]` Only when share changes are governed by members
+    ///    * Members holding at least the approval threshold, consenting to the end of
+    ///      member governance
+    RenounceAdmin {},
+
+    /// CloseDistributor instruction
+    /// Transfer the pending amount of every member, hand the shared account over to a new
+    /// owner, and zero the state account, refunding its lamports
+    /// Any remainder carried forward stays in the shared account
+    /// Native distributors empty the vault into the recipient instead of handing it over
+    /// The shared account of every registered mint is handed over too, and its ledger refunded
+    /// Not available while a share table is pending, nor on distributors nested in another one,
+    /// which would keep paying into them
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]`
+    ///    * State account
+    ///
+    /// 1. `[writable]`
+    ///    * Shared account: token account that holds tokens to be shared between members
+    ///    * Should be the shared account recorded in state, with the recorded mint, owned by the PDA
+    ///
+    /// 2. `[]` The token program account
+    ///
+    /// 3. `[]` The PDA account
+    ///    * Derived from the state account key and the bump seed stored in state
+    ///
+    /// 4. `[writable]`
+    ///    * Recipient of the state account lamports, and of the vault lamports for native distributors
+    ///      and the ledger account lamports
+    ///
+    /// 5. `[writable]` ... `[writable]`
+    ///    * Destination token accounts, one per member in member order
+    ///    * Each should hold the recorded mint and be owned by its member,
+    ///      or be the member wallet for native distributors
+    ///    * For a member flagged as a nested distributor, the shared account recorded
+    ///      by that distributor
+    ///
+    /// 5 + member count. `[writable]` ... `[writable]`
+    ///    * For each member flagged as a nested distributor, in member order: its state account,
+    ///      whose nested flag is cleared
+    ///
+    /// 5 + member count + nested distributor count. `[writable]` ... `[writable]`
+    ///    * For each registered mint: its ledger account, its shared account, then
+    ///      destination token accounts of that mint, one per member in member order,
+    ///      then the ledger account of that mint of each nested distributor, in member order
+    ///
+    /// Remaining accounts. `[signer]` ... `[signer]`
+    ///    * Accounts authorizing the close: members holding at least the approval threshold
+    ///      when share changes are governed by members, the admin otherwise
+    ///    * A distributor without admin nor member governance cannot be closed
+    ///
+    /// Instruction data (after the tag):
+    /// * 32 bytes public key of the new owner of the shared account
+    CloseDistributor { new_shared_account_owner: Pubkey },
+
+    /// ContestSharesUpdate instruction
+    /// Contest the pending share table before it takes effect; once members holding more than
+    /// half of the current shares contest it, the pending share table is dropped
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Account of the member contesting
+    ///
+    /// 1. `[writable]`
+    ///    * State account
+    ContestSharesUpdate {},
+
+    /// RegisterMint instruction
+    /// Register the shared account of another mint, governed by the same share table,
+    /// transferring its ownership to the PDA and creating its ledger
+    /// Not available while a share table is pending, since share changes should take effect
+    /// on every mint at once
+    ///
+    /// Once mints are registered, instructions that change the share table or the members
+    /// also settle every registered mint, and expect its accounts after their own
+    ///
+    /// Accounts expected:
+    /// 0. `[signer, writable]`
+    ///    * Owner of the shared account to register
+    ///    * Funds the ledger account
+    ///
+    /// 1. `[writable]`
+    ///    * State account
+    ///
+    /// 2. `[writable]`
+    ///    * Shared account: token account of a mint other than the distributor mint
+    ///    * Its ownership will be transfered to the PDA
+    ///    * Should have no delegate, and no close authority other than the PDA
+    ///
+    /// 3. `[writable]`
+    ///    * Ledger account: the PDA derived from the state account key and the mint
+    ///      (see `state::find_mint_ledger_address`), created by this instruction
+    ///
+    /// 4. `[]` The token program account
+    ///
+    /// 5. `[]` The system program account
+    ///
+    /// 6. `[signer]` ... `[signer]`
+    ///    * Accounts authorizing the registration: members holding at least the approval
+    ///      threshold when share changes are governed by members, the admin otherwise
+    RegisterMint {},
+
+    /// NestDistributor instruction
+    /// Flag a member as another royalty distributor, whose state account is the member
+    /// public key: its payouts then go into the shared account of that distributor
+    ///
+    /// Nesting is checked at configuration time: a distributor nested in another one
+    /// cannot nest distributors of its own, which rules out cycles, so trees are built
+    /// from the bottom up, and no distributor may have more than `state::MAX_NESTING_DEPTH`
+    /// levels below it
+    /// A nested distributor cannot be closed until it is removed from its parent or the parent
+    /// is closed, which clears its nested flag
+    ///
+    /// Accounts expected:
+    /// 0. `[writable]`
+    ///    * State account of the parent distributor
+    ///
+    /// 1. `[writable]`
+    ///    * State account of the distributor to nest, with the same mint
+    ///    * Should not be nested already, nor have a share table that can never change,
+    ///      since it could not consent to the nesting
+    ///
+    /// 2. `[signer]` ... `[signer]`
+    ///    * Accounts authorizing the nesting, for both distributors: members holding at least
+    ///      the approval threshold when share changes are governed by members, the admin otherwise
+    ///
+    /// Instruction data (after the tag):
+    /// * `u16` little-endian index of the member to flag
+    NestDistributor { member_index: u16 },
+
+    /// DistributeTree instruction
+    /// Withdraw from a distributor and, in the same instruction, from the distributors
+    /// nested below it, each receiving its payout before distributing it in turn
+    ///
+    /// Accounts expected:
+    /// 0. `[signer]`
+    ///    * Account executing the distribution
+    ///    * Should be allowed by the distribution policy of every distributor
+    ///
+    /// 1. `[]` The token program account
+    ///
+    /// 2. `[writable]` ... `[writable]`
+    ///    * For each distributor, the root first and every nested distributor after its
+    ///      parent: its state account, shared account and PDA account, then its destination
+    ///      accounts, one per member in member order, as with Withdraw
+    ///    * The destination account of a nested member is the shared account of the
+    ///      nested distributor, followed after the destinations by the state accounts of
+    ///      the nested distributors, in member order, as with Withdraw
+    ///    * For distributors retaining receipts, followed by the receipt account
+    ///      and the system program account, as with Withdraw
+    DistributeTree {},
+
+    /// InitFromMetadata instruction
+    /// Initialize the royalty distributor as InitRoyaltyDistributor does, with the creators
+    /// of a Metaplex Token Metadata account as members, so the split mirrors what
+    /// marketplaces display: each creator share, in percent, becomes shares in basis points
+    /// Creators without share are left out
+    ///
+    /// Accounts expected:
+    /// 0. ... 4. Same accounts as InitRoyaltyDistributor
+    ///
+    /// 5. `[]`
+    ///    * Metadata account, owned by the Token Metadata program (see `metadata::id`)
+    ///
+    /// Instruction data (after the tag):
+    /// * Same configuration as InitRoyaltyDistributor, without the members
+    /// * `u8` flag to reject unverified creators (0: accept, 1: reject)
+    ///
+    /// The state account should be at least `RoyaltyDistributor::get_packed_len(creator count)` bytes
+    InitFromMetadata {
+        config: DistributorConfig,
+        require_verified_creators: bool,
+    },
+
+    /// CloseReceipt instruction
+    /// Close a distribution receipt once its retention period elapsed,
+    /// refunding its rent to the account that funded it
+    ///
+    /// Accounts expected:
+    /// 0. `[signer, writable]`
+    ///    * Payer of the receipt, receiving its lamports
+    ///
+    /// 1. `[writable]`
+    ///    * Receipt account
+    CloseReceipt {},
 }
 
 impl RoyaltyDistributorInstruction {
     pub fn unpack(input: &[u8]) -> Result<Self, ProgramError> {
-        let (tag, _) = input.split_first().ok_or(InvalidInstruction)?;
+        let (tag, rest) = input.split_first().ok_or(InvalidInstruction)?;
 
         Ok(match tag {
-            0 => Self::unpack_royalty_distributor(),
-            1 => Self::Withdraw {},
+            0 => Self::unpack_royalty_distributor(rest)?,
+            1 => Self::Withdraw {
+                mint: Self::unpack_optional_pubkey(rest)?,
+            },
+            2 => Self::Claim {
+                mint: Self::unpack_optional_pubkey(rest)?,
+            },
+            3 => Self::UpdateShares {
+                shares: Self::unpack_shares(rest)?,
+            },
+            4 => {
+                let (pubkey, rest) = Self::unpack_pubkey(rest)?;
+                Self::AddMember {
+                    pubkey,
+                    shares: Self::unpack_shares(rest)?,
+                }
+            }
+            5 => {
+                let (member_index, rest) = Self::unpack_u16(rest)?;
+                Self::RemoveMember {
+                    member_index,
+                    shares: Self::unpack_shares(rest)?,
+                }
+            }
+            6 => Self::TransferMembership {
+                require_new_member_signature: match rest {
+                    [0] => false,
+                    [1] => true,
+                    _ => return Err(InvalidInstruction.into()),
+                },
+            },
+            7 => {
+                let (expires_at, rest) = Self::unpack_i64(rest)?;
+                Self::ProposeShares {
+                    expires_at,
+                    shares: Self::unpack_shares(rest)?,
+                }
+            }
+            8 => Self::ApproveProposal {},
+            9 => Self::ExecuteProposal {},
+            10 => {
+                let (pending_admin, _) = Self::unpack_pubkey(rest)?;
+                Self::ProposeAdmin { pending_admin }
+            }
+            11 => Self::AcceptAdmin {},
+            12 => Self::RenounceAdmin {},
+            13 => {
+                let (new_shared_account_owner, _) = Self::unpack_pubkey(rest)?;
+                Self::CloseDistributor {
+                    new_shared_account_owner,
+                }
+            }
+            14 => Self::ContestSharesUpdate {},
+            15 => Self::RegisterMint {},
+            16 => {
+                let (member_index, _) = Self::unpack_u16(rest)?;
+                Self::NestDistributor { member_index }
+            }
+            17 => Self::DistributeTree {},
+            18 => {
+                let (config, rest) = Self::unpack_config(rest)?;
+                Self::InitFromMetadata {
+                    config,
+                    require_verified_creators: match rest {
+                        [0] => false,
+                        [1] => true,
+                        _ => return Err(InvalidInstruction.into()),
+                    },
+                }
+            }
+            19 => Self::CloseReceipt {},
             _ => return Err(InvalidInstruction.into()),
         })
     }
 
-    fn unpack_royalty_distributor() -> Self {
-        Self::InitRoyaltyDistributor {
-            member_1_shares: 3800,
-            member_2_shares: 2000,
-            member_3_shares: 1000,
-            member_4_shares: 700,
-            member_5_shares: 700,
-            member_6_shares: 600,
-            member_7_shares: 600,
-            member_8_shares: 600,
+    fn unpack_royalty_distributor(input: &[u8]) -> Result<Self, ProgramError> {
+        let (config, rest) = Self::unpack_config(input)?;
+
+        let members = Self::unpack_members(rest)?;
+        if members.is_empty() {
+            return Err(InvalidInstruction.into());
+        }
+
+        Ok(Self::InitRoyaltyDistributor { config, members })
+    }
+
+    fn unpack_config(input: &[u8]) -> Result<(DistributorConfig, &[u8]), ProgramError> {
+        if input.len() < RemainderPolicy::LEN + DistributionPolicy::LEN {
+            return Err(InvalidInstruction.into());
+        }
+        let (remainder_policy, rest) = input.split_at(RemainderPolicy::LEN);
+        let remainder_policy =
+            RemainderPolicy::unpack_from_slice(remainder_policy).map_err(|_| InvalidInstruction)?;
+        let (distribution_policy, rest) = rest.split_at(DistributionPolicy::LEN);
+        let distribution_policy = DistributionPolicy::unpack_from_slice(distribution_policy)
+            .map_err(|_| InvalidInstruction)?;
+        let (admin, rest) = Self::unpack_pubkey(rest)?;
+        let (approval_threshold, rest) = Self::unpack_u16(rest)?;
+        let (timelock, rest) = Self::unpack_i64(rest)?;
+        let (is_native, rest) = match rest.split_first() {
+            Some((0, rest)) => (false, rest),
+            Some((1, rest)) => (true, rest),
+            _ => return Err(InvalidInstruction.into()),
+        };
+        if rest.len() < ReceiptPolicy::LEN {
+            return Err(InvalidInstruction.into());
+        }
+        let (receipt_policy, rest) = rest.split_at(ReceiptPolicy::LEN);
+        let receipt_policy =
+            ReceiptPolicy::unpack_from_slice(receipt_policy).map_err(|_| InvalidInstruction)?;
+
+        Ok((
+            DistributorConfig {
+                remainder_policy,
+                distribution_policy,
+                admin,
+                approval_threshold,
+                timelock,
+                is_native,
+                receipt_policy,
+            },
+            rest,
+        ))
+    }
+
+    fn unpack_members(input: &[u8]) -> Result<Vec<Member>, ProgramError> {
+        // Member count: u16, followed by the member entries (pubkey: 32 bytes, shares: u16)
+        let (member_count, rest) = Self::unpack_u16(input)?;
+        let member_count = member_count as usize;
+
+        if rest.len() != member_count * 34 {
+            return Err(InvalidInstruction.into());
+        }
+
+        Ok(rest
+            .chunks_exact(34)
+            .map(|member| {
+                let member = array_ref![member, 0, 34];
+                let (pubkey, shares) = array_refs![member, 32, 2];
+                Member::new(Pubkey::new_from_array(*pubkey), u16::from_le_bytes(*shares))
+            })
+            .collect())
+    }
+
+    fn unpack_shares(input: &[u8]) -> Result<Vec<u16>, ProgramError> {
+        // Member count: u16, followed by the shares of each member
+        let (member_count, rest) = Self::unpack_u16(input)?;
+        if member_count == 0 || rest.len() != member_count as usize * 2 {
+            return Err(InvalidInstruction.into());
+        }
+
+        Ok(rest
+            .chunks_exact(2)
+            .map(|shares| u16::from_le_bytes([shares[0], shares[1]]))
+            .collect())
+    }
+
+    fn unpack_pubkey(input: &[u8]) -> Result<(Pubkey, &[u8]), ProgramError> {
+        if input.len() < 32 {
+            return Err(InvalidInstruction.into());
+        }
+        let (pubkey, rest) = input.split_at(32);
+        Ok((Pubkey::new(pubkey), rest))
+    }
+
+    fn unpack_optional_pubkey(input: &[u8]) -> Result<Option<Pubkey>, ProgramError> {
+        match input.len() {
+            0 => Ok(None),
+            32 => Ok(Some(Pubkey::new(input))),
+            _ => Err(InvalidInstruction.into()),
+        }
+    }
+
+    fn unpack_u16(input: &[u8]) -> Result<(u16, &[u8]), ProgramError> {
+        if input.len() < 2 {
+            return Err(InvalidInstruction.into());
+        }
+        let (value, rest) = input.split_at(2);
+        Ok((u16::from_le_bytes([value[0], value[1]]), rest))
+    }
+
+    fn unpack_i64(input: &[u8]) -> Result<(i64, &[u8]), ProgramError> {
+        if input.len() < 8 {
+            return Err(InvalidInstruction.into());
+        }
+        let (value, rest) = input.split_at(8);
+        let value = array_ref![value, 0, 8];
+        Ok((i64::from_le_bytes(*value), rest))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn init_data(member_count: u16, members: &[(Pubkey, u16)]) -> Vec<u8> {
+        let mut data = vec![0];
+        let mut policies = [0; RemainderPolicy::LEN + DistributionPolicy::LEN + ReceiptPolicy::LEN];
+        RemainderPolicy::CarryForward.pack_into_slice(&mut policies[..RemainderPolicy::LEN]);
+        DistributionPolicy::Permissionless.pack_into_slice(
+            &mut policies[RemainderPolicy::LEN..RemainderPolicy::LEN + DistributionPolicy::LEN],
+        );
+        ReceiptPolicy::Disabled
+            .pack_into_slice(&mut policies[RemainderPolicy::LEN + DistributionPolicy::LEN..]);
+
+        data.extend_from_slice(&policies[..RemainderPolicy::LEN + DistributionPolicy::LEN]);
+        data.extend_from_slice(&[0; 32]);
+        data.extend_from_slice(&0u16.to_le_bytes());
+        data.extend_from_slice(&0i64.to_le_bytes());
+        data.push(0);
+        data.extend_from_slice(&policies[RemainderPolicy::LEN + DistributionPolicy::LEN..]);
+
+        data.extend_from_slice(&member_count.to_le_bytes());
+        for (pubkey, shares) in members {
+            data.extend_from_slice(pubkey.as_ref());
+            data.extend_from_slice(&shares.to_le_bytes());
+        }
+        data
+    }
+
+    #[test]
+    fn test_unpack_init_royalty_distributor() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let data = init_data(2, &[(first, 6000), (second, 4000)]);
+
+        match RoyaltyDistributorInstruction::unpack(&data).unwrap() {
+            RoyaltyDistributorInstruction::InitRoyaltyDistributor { config, members } => {
+                assert_eq!(config.remainder_policy, RemainderPolicy::CarryForward);
+                assert_eq!(
+                    config.distribution_policy,
+                    DistributionPolicy::Permissionless
+                );
+                assert_eq!(config.receipt_policy, ReceiptPolicy::Disabled);
+                assert!(!config.is_native);
+                assert_eq!(members.len(), 2);
+                assert_eq!(members[0].pubkey, first);
+                assert_eq!(members[0].shares, 6000);
+                assert_eq!(members[1].pubkey, second);
+                assert_eq!(members[1].shares, 4000);
+            }
+            _ => panic!("unexpected instruction"),
         }
     }
+
+    #[test]
+    fn test_unpack_init_truncated_members() {
+        let data = init_data(
+            2,
+            &[(Pubkey::new_unique(), 6000), (Pubkey::new_unique(), 4000)],
+        );
+        for len in 1..data.len() {
+            assert_eq!(
+                RoyaltyDistributorInstruction::unpack(&data[..len]).err(),
+                Some(InvalidInstruction.into()),
+                "length {}",
+                len
+            );
+        }
+    }
+
+    #[test]
+    fn test_unpack_init_trailing_bytes() {
+        // Fewer members than the entries that follow
+        let data = init_data(
+            1,
+            &[(Pubkey::new_unique(), 6000), (Pubkey::new_unique(), 4000)],
+        );
+        assert_eq!(
+            RoyaltyDistributorInstruction::unpack(&data).err(),
+            Some(InvalidInstruction.into())
+        );
+
+        let mut data = init_data(1, &[(Pubkey::new_unique(), 10000)]);
+        data.push(0);
+        assert_eq!(
+            RoyaltyDistributorInstruction::unpack(&data).err(),
+            Some(InvalidInstruction.into())
+        );
+    }
+
+    #[test]
+    fn test_unpack_init_no_members() {
+        let data = init_data(0, &[]);
+        assert_eq!(
+            RoyaltyDistributorInstruction::unpack(&data).err(),
+            Some(InvalidInstruction.into())
+        );
+    }
 }
@@ -1,5 +1,7 @@
 pub mod entrypoint;
 pub mod instruction;
 pub mod error;
+pub mod event;
+pub mod metadata;
 pub mod processor;
 pub mod state;
\ No newline at end of file
@@ -0,0 +1,223 @@
+//! Reader of the creators declared in Metaplex Token Metadata accounts
+
+use std::convert::TryInto;
+
+use solana_program::{program_error::ProgramError, pubkey::Pubkey};
+
+use crate::{
+    error::RoyaltyDistributorError::{InvalidMetadata, InvalidSharesTotal, UnverifiedCreator},
+    state::Member,
+};
+
+solana_program::declare_id!("metaqbxxUerdq28cj1RbAWkYQm3ybzjb6a8bt518x1s");
+
+/// Key of a metadata account, first byte of its data
+const METADATA_KEY: u8 = 4;
+
+/// Creator declared in a metadata account
+pub struct Creator {
+    pub address: Pubkey,
+    pub verified: bool,
+    // Share of the royalties, in percent
+    pub share: u8,
+}
+
+/// Unpacks the creators of a metadata account, none if it declares none
+///
+/// Metadata accounts are Borsh encoded: key, update authority, mint, then name,
+/// symbol and URI strings, seller fee basis points and optional creators
+pub fn unpack_creators(input: &[u8]) -> Result<Vec<Creator>, ProgramError> {
+    let (key, rest) = input.split_first().ok_or(InvalidMetadata)?;
+    if *key != METADATA_KEY {
+        return Err(InvalidMetadata.into());
+    }
+
+    // Update authority and mint
+    let (_, rest) = split(rest, 64)?;
+
+    // Name, symbol and URI
+    let (_, rest) = unpack_string(rest)?;
+    let (_, rest) = unpack_string(rest)?;
+    let (_, rest) = unpack_string(rest)?;
+
+    // Seller fee basis points
+    let (_, rest) = split(rest, 2)?;
+
+    let rest = match rest.split_first() {
+        Some((0, _)) => return Ok(Vec::new()),
+        Some((1, rest)) => rest,
+        _ => return Err(InvalidMetadata.into()),
+    };
+    let (creator_count, rest) = unpack_u32(rest)?;
+
+    // Each creator: 32 bytes address, `u8` verified flag, `u8` share
+    let (creators, _) = split(rest, creator_count as usize * 34)?;
+    creators
+        .chunks_exact(34)
+        .map(|creator| {
+            Ok(Creator {
+                address: Pubkey::new(&creator[..32]),
+                verified: match creator[32] {
+                    0 => false,
+                    1 => true,
+                    _ => return Err(InvalidMetadata.into()),
+                },
+                share: creator[33],
+            })
+        })
+        .collect()
+}
+
+/// Members for the creators with a share, percents becoming basis points
+///
+/// The creator shares should total 100 percent
+pub fn creator_members(
+    creators: &[Creator],
+    require_verified: bool,
+) -> Result<Vec<Member>, ProgramError> {
+    let mut members = Vec::with_capacity(creators.len());
+    let mut total: u32 = 0;
+    for creator in creators.iter().filter(|creator| creator.share > 0) {
+        if require_verified && !creator.verified {
+            return Err(UnverifiedCreator.into());
+        }
+        total += creator.share as u32;
+        members.push(Member::new(creator.address, creator.share as u16 * 100));
+    }
+    if total != 100 {
+        return Err(InvalidSharesTotal.into());
+    }
+    Ok(members)
+}
+
+fn split(input: &[u8], len: usize) -> Result<(&[u8], &[u8]), ProgramError> {
+    if input.len() < len {
+        return Err(InvalidMetadata.into());
+    }
+    Ok(input.split_at(len))
+}
+
+fn unpack_u32(input: &[u8]) -> Result<(u32, &[u8]), ProgramError> {
+    let (value, rest) = split(input, 4)?;
+    let value = value.try_into().map_err(|_| InvalidMetadata)?;
+    Ok((u32::from_le_bytes(value), rest))
+}
+
+fn unpack_string(input: &[u8]) -> Result<(&[u8], &[u8]), ProgramError> {
+    let (len, rest) = unpack_u32(input)?;
+    split(rest, len as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn metadata(creators: Option<&[(Pubkey, u8, u8)]>) -> Vec<u8> {
+        let mut data = vec![METADATA_KEY];
+        data.extend_from_slice(&[0; 64]);
+        for string in &["Name", "SYM", "https://example.com/0.json"] {
+            data.extend_from_slice(&(string.len() as u32).to_le_bytes());
+            data.extend_from_slice(string.as_bytes());
+        }
+        data.extend_from_slice(&500u16.to_le_bytes());
+        match creators {
+            None => data.push(0),
+            Some(creators) => {
+                data.push(1);
+                data.extend_from_slice(&(creators.len() as u32).to_le_bytes());
+                for (address, verified, share) in creators {
+                    data.extend_from_slice(address.as_ref());
+                    data.push(*verified);
+                    data.push(*share);
+                }
+            }
+        }
+        data
+    }
+
+    #[test]
+    fn test_unpack_creators() {
+        let first = Pubkey::new_unique();
+        let second = Pubkey::new_unique();
+        let data = metadata(Some(&[(first, 1, 70), (second, 0, 30)]));
+
+        let creators = unpack_creators(&data).unwrap();
+        assert_eq!(creators.len(), 2);
+        assert_eq!(creators[0].address, first);
+        assert!(creators[0].verified);
+        assert_eq!(creators[0].share, 70);
+        assert_eq!(creators[1].address, second);
+        assert!(!creators[1].verified);
+        assert_eq!(creators[1].share, 30);
+
+        let members = creator_members(&creators, false).unwrap();
+        assert_eq!(members.len(), 2);
+        assert_eq!(members[0].pubkey, first);
+        assert_eq!(members[0].shares, 7000);
+        assert_eq!(members[1].pubkey, second);
+        assert_eq!(members[1].shares, 3000);
+    }
+
+    #[test]
+    fn test_unpack_creators_none() {
+        let creators = unpack_creators(&metadata(None)).unwrap();
+        assert!(creators.is_empty());
+    }
+
+    #[test]
+    fn test_unpack_creators_truncated() {
+        let data = metadata(Some(&[(Pubkey::new_unique(), 1, 100)]));
+        for len in 0..data.len() {
+            assert_eq!(
+                unpack_creators(&data[..len]).err(),
+                Some(InvalidMetadata.into()),
+                "length {}",
+                len
+            );
+        }
+
+        let mut data = metadata(None);
+        data[0] = METADATA_KEY + 1;
+        assert_eq!(unpack_creators(&data).err(), Some(InvalidMetadata.into()));
+
+        let data = metadata(Some(&[(Pubkey::new_unique(), 2, 100)]));
+        assert_eq!(unpack_creators(&data).err(), Some(InvalidMetadata.into()));
+    }
+
+    #[test]
+    fn test_creator_members_unverified() {
+        let verified = Pubkey::new_unique();
+        let data = metadata(Some(&[(verified, 1, 100), (Pubkey::new_unique(), 0, 0)]));
+        let creators = unpack_creators(&data).unwrap();
+        // Creators without share are left out, verified or not
+        let members = creator_members(&creators, true).unwrap();
+        assert_eq!(members.len(), 1);
+        assert_eq!(members[0].pubkey, verified);
+
+        let data = metadata(Some(&[
+            (Pubkey::new_unique(), 1, 50),
+            (Pubkey::new_unique(), 0, 50),
+        ]));
+        let creators = unpack_creators(&data).unwrap();
+        assert_eq!(
+            creator_members(&creators, true).err(),
+            Some(UnverifiedCreator.into())
+        );
+        assert_eq!(creator_members(&creators, false).unwrap().len(), 2);
+    }
+
+    #[test]
+    fn test_creator_members_shares_total() {
+        for shares in &[[60, 30], [60, 50], [0, 0]] {
+            let data = metadata(Some(&[
+                (Pubkey::new_unique(), 1, shares[0]),
+                (Pubkey::new_unique(), 1, shares[1]),
+            ]));
+            let creators = unpack_creators(&data).unwrap();
+            assert_eq!(
+                creator_members(&creators, false).err(),
+                Some(InvalidSharesTotal.into())
+            );
+        }
+    }
+}
@@ -1,21 +1,29 @@
-use std::str::FromStr;
-
 use solana_program::{
     account_info::{next_account_info, AccountInfo},
     entrypoint::ProgramResult,
     msg,
     program::{invoke, invoke_signed},
     program_error::ProgramError,
+    program_option::COption,
     program_pack::{IsInitialized, Pack},
     pubkey::Pubkey,
-    sysvar::{rent::Rent, Sysvar},
+    system_instruction, system_program,
+    sysvar::{clock::Clock, rent::Rent, Sysvar},
 };
 
 use spl_token::{instruction::AuthorityType::AccountOwner, state::Account as TokenAccount};
 
 use crate::{
-    error::RoyaltyDistributorError, instruction::RoyaltyDistributorInstruction,
-    state::RoyaltyDistributor,
+    error::RoyaltyDistributorError,
+    event::{ConfigSnapshot, Event},
+    instruction::{DistributorConfig, RoyaltyDistributorInstruction},
+    metadata,
+    state::{
+        find_authority_address, find_mint_ledger_address, find_receipt_address, DistributionPolicy,
+        LedgerEntry, Member, MintLedger, Proposal, Receipt, ReceiptEntry, ReceiptPolicy,
+        RemainderPolicy, RoyaltyDistributor, AUTHORITY_SEED, MAX_NESTING_DEPTH, MINT_LEDGER_SEED,
+        RECEIPT_SEED, TOTAL_SHARES,
+    },
 };
 
 pub struct Processor;
@@ -28,354 +36,2753 @@ impl Processor {
     ) -> ProgramResult {
         let instruction = RoyaltyDistributorInstruction::unpack(instruction_data)?;
         match instruction {
-            RoyaltyDistributorInstruction::InitRoyaltyDistributor {
-                member_1_shares,
-                member_2_shares,
-                member_3_shares,
-                member_4_shares,
-                member_5_shares,
-                member_6_shares,
-                member_7_shares,
-                member_8_shares,
-            } => {
+            RoyaltyDistributorInstruction::InitRoyaltyDistributor { config, members } => {
                 msg!("Instruction: Init Royalty Distributor");
-                Self::process_init_royalty_distributor(
+                Self::process_init_royalty_distributor(accounts, config, members, program_id)
+            }
+            RoyaltyDistributorInstruction::Withdraw { mint } => {
+                msg!("Instruction: Withdraw");
+                Self::process_withdraw(accounts, mint, program_id)
+            }
+            RoyaltyDistributorInstruction::Claim { mint } => {
+                msg!("Instruction: Claim");
+                Self::process_claim(accounts, mint, program_id)
+            }
+            RoyaltyDistributorInstruction::UpdateShares { shares } => {
+                msg!("Instruction: Update Shares");
+                Self::process_update_shares(accounts, shares, program_id)
+            }
+            RoyaltyDistributorInstruction::AddMember { pubkey, shares } => {
+                msg!("Instruction: Add Member");
+                Self::process_add_member(accounts, pubkey, shares, program_id)
+            }
+            RoyaltyDistributorInstruction::RemoveMember {
+                member_index,
+                shares,
+            } => {
+                msg!("Instruction: Remove Member");
+                Self::process_remove_member(accounts, member_index, shares, program_id)
+            }
+            RoyaltyDistributorInstruction::TransferMembership {
+                require_new_member_signature,
+            } => {
+                msg!("Instruction: Transfer Membership");
+                Self::process_transfer_membership(
                     accounts,
-                    member_1_shares,
-                    member_2_shares,
-                    member_3_shares,
-                    member_4_shares,
-                    member_5_shares,
-                    member_6_shares,
-                    member_7_shares,
-                    member_8_shares,
+                    require_new_member_signature,
                     program_id,
                 )
             }
-            RoyaltyDistributorInstruction::Withdraw {} => {
-                msg!("Instruction: Withdraw");
-                Self::process_withdraw(accounts, program_id)
+            RoyaltyDistributorInstruction::ProposeShares { expires_at, shares } => {
+                msg!("Instruction: Propose Shares");
+                Self::process_propose_shares(accounts, expires_at, shares, program_id)
+            }
+            RoyaltyDistributorInstruction::ApproveProposal {} => {
+                msg!("Instruction: Approve Proposal");
+                Self::process_approve_proposal(accounts, program_id)
+            }
+            RoyaltyDistributorInstruction::ExecuteProposal {} => {
+                msg!("Instruction: Execute Proposal");
+                Self::process_execute_proposal(accounts, program_id)
+            }
+            RoyaltyDistributorInstruction::ProposeAdmin { pending_admin } => {
+                msg!("Instruction: Propose Admin");
+                Self::process_propose_admin(accounts, pending_admin, program_id)
+            }
+            RoyaltyDistributorInstruction::AcceptAdmin {} => {
+                msg!("Instruction: Accept Admin");
+                Self::process_accept_admin(accounts, program_id)
+            }
+            RoyaltyDistributorInstruction::RenounceAdmin {} => {
+                msg!("Instruction: Renounce Admin");
+                Self::process_renounce_admin(accounts, program_id)
+            }
+            RoyaltyDistributorInstruction::CloseDistributor {
+                new_shared_account_owner,
+            } => {
+                msg!("Instruction: Close Distributor");
+                Self::process_close_distributor(accounts, new_shared_account_owner, program_id)
+            }
+            RoyaltyDistributorInstruction::ContestSharesUpdate {} => {
+                msg!("Instruction: Contest Shares Update");
+                Self::process_contest_shares_update(accounts, program_id)
+            }
+            RoyaltyDistributorInstruction::RegisterMint {} => {
+                msg!("Instruction: Register Mint");
+                Self::process_register_mint(accounts, program_id)
+            }
+            RoyaltyDistributorInstruction::NestDistributor { member_index } => {
+                msg!("Instruction: Nest Distributor");
+                Self::process_nest_distributor(accounts, member_index, program_id)
+            }
+            RoyaltyDistributorInstruction::DistributeTree {} => {
+                msg!("Instruction: Distribute Tree");
+                Self::process_distribute_tree(accounts, program_id)
+            }
+            RoyaltyDistributorInstruction::InitFromMetadata {
+                config,
+                require_verified_creators,
+            } => {
+                msg!("Instruction: Init From Metadata");
+                Self::process_init_from_metadata(
+                    accounts,
+                    config,
+                    require_verified_creators,
+                    program_id,
+                )
+            }
+            RoyaltyDistributorInstruction::CloseReceipt {} => {
+                msg!("Instruction: Close Receipt");
+                Self::process_close_receipt(accounts, program_id)
             }
         }
     }
 
-    fn process_init_royalty_distributor(
-        accounts: &[AccountInfo],
-        member_1_shares: u16,
-        member_2_shares: u16,
-        member_3_shares: u16,
-        member_4_shares: u16,
-        member_5_shares: u16,
-        member_6_shares: u16,
-        member_7_shares: u16,
-        member_8_shares: u16,
+    /// Reports the unpack errors of an account of this program as `not_initialized`
+    /// or `invalid_data`, so they name the account that failed
+    fn account_data_error(
+        err: ProgramError,
+        not_initialized: RoyaltyDistributorError,
+        invalid_data: RoyaltyDistributorError,
+    ) -> ProgramError {
+        match err {
+            ProgramError::UninitializedAccount => not_initialized.into(),
+            ProgramError::InvalidAccountData => invalid_data.into(),
+            err => err,
+        }
+    }
+
+    fn state_account_error(err: ProgramError) -> ProgramError {
+        Self::account_data_error(
+            err,
+            RoyaltyDistributorError::StateAccountNotInitialized,
+            RoyaltyDistributorError::InvalidStateAccountData,
+        )
+    }
+
+    fn proposal_account_error(err: ProgramError) -> ProgramError {
+        Self::account_data_error(
+            err,
+            RoyaltyDistributorError::ProposalAccountNotInitialized,
+            RoyaltyDistributorError::InvalidProposalAccountData,
+        )
+    }
+
+    fn ledger_account_error(err: ProgramError) -> ProgramError {
+        Self::account_data_error(
+            err,
+            RoyaltyDistributorError::LedgerAccountNotInitialized,
+            RoyaltyDistributorError::InvalidLedgerAccountData,
+        )
+    }
+
+    fn receipt_account_error(err: ProgramError) -> ProgramError {
+        Self::account_data_error(
+            err,
+            RoyaltyDistributorError::ReceiptAccountNotInitialized,
+            RoyaltyDistributorError::InvalidReceiptAccountData,
+        )
+    }
+
+    /// Checks that `shared_acct` is the shared account recorded in state and returns its balance
+    ///
+    /// For SPL distributors, the shared account should hold the recorded mint and be owned
+    /// by the distributor PDA. For native distributors, the shared account is the PDA vault,
+    /// owned by this program, whose rent-exempt minimum is not part of the balance.
+    fn shared_balance(
+        shared_acct: &AccountInfo,
+        state_acct_data: &RoyaltyDistributor,
+        pda: &Pubkey,
         program_id: &Pubkey,
+    ) -> Result<u64, ProgramError> {
+        if state_acct_data.is_native {
+            if *shared_acct.key != state_acct_data.shared_account {
+                return Err(RoyaltyDistributorError::SharedAccountMismatch.into());
+            }
+            if shared_acct.owner != program_id {
+                return Err(RoyaltyDistributorError::InvalidVaultOwner.into());
+            }
+            let rent_exempt_minimum = Rent::get()?.minimum_balance(shared_acct.data_len());
+            return Ok(shared_acct.lamports().saturating_sub(rent_exempt_minimum));
+        }
+
+        Self::shared_token_balance(
+            shared_acct,
+            &state_acct_data.shared_account,
+            &state_acct_data.mint,
+            pda,
+        )
+    }
+
+    /// Checks that `shared_acct` is the token account `shared_account`, holds `mint`
+    /// and is owned by the distributor PDA, returning its balance
+    /// No delegate nor close authority other than the PDA may move its funds either
+    fn shared_token_balance(
+        shared_acct: &AccountInfo,
+        shared_account: &Pubkey,
+        mint: &Pubkey,
+        pda: &Pubkey,
+    ) -> Result<u64, ProgramError> {
+        if shared_acct.key != shared_account {
+            return Err(RoyaltyDistributorError::SharedAccountMismatch.into());
+        }
+        if *shared_acct.owner != spl_token::id() {
+            return Err(RoyaltyDistributorError::SharedAccountNotTokenAccount.into());
+        }
+        let shared_acct_data = TokenAccount::unpack(&shared_acct.data.borrow())
+            .map_err(|_| RoyaltyDistributorError::SharedAccountNotTokenAccount)?;
+        if shared_acct_data.mint != *mint {
+            return Err(RoyaltyDistributorError::MintMismatch.into());
+        }
+        if shared_acct_data.owner != *pda {
+            return Err(RoyaltyDistributorError::InvalidSharedAccountOwner.into());
+        }
+        Self::check_shared_account_authorities(&shared_acct_data, pda)?;
+
+        Ok(shared_acct_data.amount)
+    }
+
+    /// Checks that only the distributor PDA may move or close the funds of a shared
+    /// token account: handing the account over to the PDA keeps its delegate and
+    /// close authority in place
+    fn check_shared_account_authorities(
+        shared_acct_data: &TokenAccount,
+        pda: &Pubkey,
     ) -> ProgramResult {
-        // Accounts iterator
-        let account_info_iter = &mut accounts.iter();
+        if shared_acct_data.delegate.is_some() {
+            return Err(RoyaltyDistributorError::SharedAccountDelegated.into());
+        }
+        if let COption::Some(close_authority) = shared_acct_data.close_authority {
+            if close_authority != *pda {
+                return Err(RoyaltyDistributorError::InvalidSharedAccountCloseAuthority.into());
+            }
+        }
 
-        // [Account 0] Initializer account
-        let init_acct = next_account_info(account_info_iter)?;
-        if !init_acct.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+        Ok(())
+    }
+
+    /// Checks that `destination_acct` is a token account of the distributor mint owned by
+    /// `payee` (see `Member::payee`), or `payee` itself for native distributors
+    fn check_destination_account(
+        destination_acct: &AccountInfo,
+        payee: &Pubkey,
+        state_acct_data: &RoyaltyDistributor,
+    ) -> ProgramResult {
+        if state_acct_data.is_native {
+            if destination_acct.key != payee {
+                return Err(RoyaltyDistributorError::DestinationOwnerMismatch.into());
+            }
+            return Ok(());
         }
 
-        // [Account 1] Shared account
-        // Should be internally owned by token program
-        let shared_acct = next_account_info(account_info_iter)?;
-        if *shared_acct.owner != spl_token::id() {
-            return Err(ProgramError::IncorrectProgramId);
+        Self::check_destination_token_account(destination_acct, payee, &state_acct_data.mint)
+    }
+
+    /// Checks that `destination_acct` is a token account of `mint` owned by `payee`
+    fn check_destination_token_account(
+        destination_acct: &AccountInfo,
+        payee: &Pubkey,
+        mint: &Pubkey,
+    ) -> ProgramResult {
+        if *destination_acct.owner != spl_token::id() {
+            return Err(RoyaltyDistributorError::DestinationNotTokenAccount.into());
+        }
+        let destination_acct_data = TokenAccount::unpack(&destination_acct.data.borrow())
+            .map_err(|_| RoyaltyDistributorError::DestinationNotTokenAccount)?;
+        if destination_acct_data.owner != *payee {
+            return Err(RoyaltyDistributorError::DestinationOwnerMismatch.into());
+        }
+        if destination_acct_data.mint != *mint {
+            return Err(RoyaltyDistributorError::DestinationMintMismatch.into());
         }
 
-        // [Account 2] State account
-        let state_acct = next_account_info(account_info_iter)?;
+        Ok(())
+    }
 
-        // [Account 3] Rent sysvar account
-        let rent_acct = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
-        if !rent_acct.is_exempt(state_acct.lamports(), state_acct.data_len()) {
-            return Err(RoyaltyDistributorError::NotRentExempt.into());
+    /// Checks that `nested_acct` holds the state of the nested distributor `nested_key`
+    fn unpack_nested_distributor(
+        nested_acct: &AccountInfo,
+        nested_key: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<RoyaltyDistributor, ProgramError> {
+        if nested_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+        if nested_acct.key != nested_key {
+            return Err(RoyaltyDistributorError::NestedDistributorMismatch.into());
         }
 
-        // Ensure that state account is not initialized yet
-        let mut state_acct_data = RoyaltyDistributor::unpack_unchecked(&state_acct.data.borrow())?;
-        if state_acct_data.is_initialized() {
-            return Err(ProgramError::AccountAlreadyInitialized);
+        RoyaltyDistributor::unpack(&nested_acct.data.borrow()).map_err(Self::state_account_error)
+    }
+
+    /// Checks that `destination_acct` is the shared account recorded by the nested
+    /// distributor `nested_key`: in its state account `nested_acct` when paying out the
+    /// distributor mint, in its ledger account `nested_acct` of `mint` otherwise
+    fn check_nested_destination(
+        nested_key: &Pubkey,
+        destination_acct: &AccountInfo,
+        nested_acct: &AccountInfo,
+        mint: Option<&Pubkey>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let shared_account = match mint {
+            Some(mint) => {
+                let ledger = Self::unpack_mint_ledger(nested_acct, nested_key, program_id)?;
+                if ledger.mint != *mint {
+                    return Err(RoyaltyDistributorError::MintLedgerMismatch.into());
+                }
+                ledger.shared_account
+            }
+            None => {
+                Self::unpack_nested_distributor(nested_acct, nested_key, program_id)?.shared_account
+            }
+        };
+        if *destination_acct.key != shared_account {
+            return Err(RoyaltyDistributorError::NestedDistributorMismatch.into());
         }
 
-        // [Account 4] Token program account
-        let token_program_acct = next_account_info(account_info_iter)?;
+        Ok(())
+    }
 
-        // Populate data fields on state account
-        state_acct_data.is_initialized = true;
-        state_acct_data.member_1_pubkey =
-            Pubkey::from_str("H1AdHr2rX1BGfVMbtp3Npv7AZhyZ2sWskoD6W3eZqFiM").unwrap();
-        state_acct_data.member_2_pubkey =
-            Pubkey::from_str("AnQV1u4vPRHydy4gXGj76LQe9z7BEUmPxL8o82sHQv9g").unwrap();
-        state_acct_data.member_3_pubkey =
-            Pubkey::from_str("C2jQa1DRLBcuXXVEdAvntPmUbmDztTQSrCFVy1VHCtWM").unwrap();
-        state_acct_data.member_4_pubkey =
-            Pubkey::from_str("AU1QYLdJ6euuUCt5P4V8SS22Nn8i9TRQJ6QPF1iTmFeX").unwrap();
-        state_acct_data.member_5_pubkey =
-            Pubkey::from_str("6enefqhV85FkA3uVeTtrC3HrDfPXdZNAhFaLbPjVmt8b").unwrap();
-        state_acct_data.member_6_pubkey =
-            Pubkey::from_str("ANMei1dxBtfgssiJpEhjSqr37NkFNfKT1eCBi1nbBWbg").unwrap();
-        state_acct_data.member_7_pubkey =
-            Pubkey::from_str("3B86L4BrRjm9V7sd3AjjJq5XFtyqMgCYMCTwqMMvAxgr").unwrap();
-        state_acct_data.member_8_pubkey =
-            Pubkey::from_str("AWc2anShfEraXTJ3msQVn2pEGgzxxQYNV8JA4pg32nGp").unwrap();
-        state_acct_data.member_1_shares = member_1_shares;
-        state_acct_data.member_2_shares = member_2_shares;
-        state_acct_data.member_3_shares = member_3_shares;
-        state_acct_data.member_4_shares = member_4_shares;
-        state_acct_data.member_5_shares = member_5_shares;
-        state_acct_data.member_6_shares = member_6_shares;
-        state_acct_data.member_7_shares = member_7_shares;
-        state_acct_data.member_8_shares = member_8_shares;
+    /// Checks the destination accounts of the nested distributors among `members`
+    /// against `nested_accts`, which should hold one account per nested distributor,
+    /// in member order, as expected by `check_nested_destination`
+    fn check_nested_destinations(
+        members: &[Member],
+        destination_accts: &[AccountInfo],
+        nested_accts: &[AccountInfo],
+        mint: Option<&Pubkey>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let nested_members = members
+            .iter()
+            .zip(destination_accts)
+            .filter(|(member, _)| member.is_distributor);
+        for ((member, destination_acct), nested_acct) in nested_members.zip(nested_accts) {
+            Self::check_nested_destination(
+                &member.pubkey,
+                destination_acct,
+                nested_acct,
+                mint,
+                program_id,
+            )?;
+        }
 
-        // Store information state account
-        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+        Ok(())
+    }
 
-        // Get a Program Derived Address (PDA)
-        let (pda, _bump_seed) = Pubkey::find_program_address(&[b"royalty_distributor"], program_id);
+    /// Clears the nested flag of the distributor stored in `nested_acct`, which no longer
+    /// has a parent: it may then be closed or nested again
+    fn unflag_nested_distributor(nested_acct: &AccountInfo) -> ProgramResult {
+        let mut nested_acct_data = RoyaltyDistributor::unpack(&nested_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        nested_acct_data.is_nested = false;
+        RoyaltyDistributor::pack(nested_acct_data, &mut nested_acct.data.borrow_mut())
+    }
 
-        // Create the 'change owner' instruction
-        let owner_change_ix = spl_token::instruction::set_authority(
-            token_program_acct.key, // token program id
-            shared_acct.key,        // account whose authority we would like to change
-            Some(&pda),             // account that should be the new authority of the account
-            AccountOwner,           // type of authority change
-            init_acct.key,          // current account owner
-            &[&init_acct.key],      // public keys signing the cross program invocation (CPI)
+    /// Transfers `amount` from the shared account to `destination_acct`, signed by the PDA,
+    /// or moves `amount` lamports out of the PDA vault for native distributors
+    fn transfer_from_shared<'a>(
+        is_native: bool,
+        token_program_acct: &AccountInfo<'a>,
+        shared_acct: &AccountInfo<'a>,
+        destination_acct: &AccountInfo<'a>,
+        pda_acct: &AccountInfo<'a>,
+        authority_seeds: &[&[u8]],
+        amount: u64,
+    ) -> ProgramResult {
+        if is_native {
+            let shared_lamports = shared_acct
+                .lamports()
+                .checked_sub(amount)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+            let destination_lamports = destination_acct
+                .lamports()
+                .checked_add(amount)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+            **shared_acct.lamports.borrow_mut() = shared_lamports;
+            **destination_acct.lamports.borrow_mut() = destination_lamports;
+            return Ok(());
+        }
+
+        let transfer_ix = spl_token::instruction::transfer(
+            token_program_acct.key, // token program account
+            shared_acct.key,        // source account
+            destination_acct.key,   // destination account
+            pda_acct.key,           // authority account
+            &[],                    // signer accounts
+            amount,                 // amount
         )?;
 
-        // Cross-Program Invocation (CPI)
-        msg!("Calling the token program to transfer shared account ownership ...");
-        invoke(
-            &owner_change_ix,
+        invoke_signed(
+            &transfer_ix,
             &[
                 shared_acct.clone(),
-                init_acct.clone(),
+                destination_acct.clone(),
+                pda_acct.clone(),
                 token_program_acct.clone(),
             ],
-        )?;
-
-        Ok(())
+            &[authority_seeds],
+        )
     }
 
-    fn process_withdraw(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
-        // Accounts iterator
-        let account_info_iter = &mut accounts.iter();
+    /// Validates a share table: members should be set and distinct,
+    /// each member should hold shares and shares should sum to `TOTAL_SHARES`
+    fn validate_members(members: &[Member]) -> ProgramResult {
+        let mut total_shares: u32 = 0;
 
-        // [Account 0] Account of the member executing the withdraw
-        let init_acct = next_account_info(account_info_iter)?;
-        if !init_acct.is_signer {
-            return Err(ProgramError::MissingRequiredSignature);
+        for (i, member) in members.iter().enumerate() {
+            if member.pubkey == Pubkey::default() {
+                return Err(RoyaltyDistributorError::InvalidMemberPubkey.into());
+            }
+            if members[..i]
+                .iter()
+                .any(|other| other.pubkey == member.pubkey)
+            {
+                return Err(RoyaltyDistributorError::DuplicateMember.into());
+            }
+            if member.shares == 0 {
+                return Err(RoyaltyDistributorError::ZeroShares.into());
+            }
+            total_shares += member.shares as u32;
         }
 
-        // [Account 1] State account
-        let state_acct = next_account_info(account_info_iter)?;
-
-        // Extract data from state account
-        let state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())?;
-
-        // [Account 2] Shared account
-        let shared_acct = next_account_info(account_info_iter)?;
-        let shared_acc_data = TokenAccount::unpack(&shared_acct.data.borrow())?;
-        let (pda, bump_seed) = Pubkey::find_program_address(&[b"royalty_distributor"], program_id);
+        if total_shares != TOTAL_SHARES as u32 {
+            return Err(RoyaltyDistributorError::InvalidSharesTotal.into());
+        }
 
-        // [Account 3] Token program account
-        let token_program_acct = next_account_info(account_info_iter)?;
+        Ok(())
+    }
 
-        // [Account 4] The PDA account
-        let pda_acct = next_account_info(account_info_iter)?;
+    /// Checks that `admin_acct` is the admin recorded in state
+    fn check_admin(
+        state_acct_data: &RoyaltyDistributor,
+        admin_acct: &AccountInfo,
+    ) -> ProgramResult {
+        if state_acct_data.admin == Pubkey::default() || state_acct_data.admin != *admin_acct.key {
+            return Err(RoyaltyDistributorError::InvalidAdmin.into());
+        }
 
-        // Calculate if the member can withdraw the amount requested
-        let shared_acc_balance = shared_acc_data.amount as f64;
-        let member_1_shares = state_acct_data.member_1_shares as f64;
-        let member_2_shares = state_acct_data.member_2_shares as f64;
-        let member_3_shares = state_acct_data.member_3_shares as f64;
-        let member_4_shares = state_acct_data.member_4_shares as f64;
-        let member_5_shares = state_acct_data.member_5_shares as f64;
-        let member_6_shares = state_acct_data.member_6_shares as f64;
-        let member_7_shares = state_acct_data.member_7_shares as f64;
-        let member_8_shares = state_acct_data.member_8_shares as f64;
-
-        let member1_amount = (shared_acc_balance * member_1_shares / 10000f64) as u64;
-        let member2_amount = (shared_acc_balance * member_2_shares / 10000f64) as u64;
-        let member3_amount = (shared_acc_balance * member_3_shares / 10000f64) as u64;
-        let member4_amount = (shared_acc_balance * member_4_shares / 10000f64) as u64;
-        let member5_amount = (shared_acc_balance * member_5_shares / 10000f64) as u64;
-        let member6_amount = (shared_acc_balance * member_6_shares / 10000f64) as u64;
-        let member7_amount = (shared_acc_balance * member_7_shares / 10000f64) as u64;
-        let member8_amount = (shared_acc_balance * member_8_shares / 10000f64) as u64;
-
-        // Withdraw transfer instruction
-        let withdraw_transfer_ix1 = spl_token::instruction::transfer(
-            token_program_acct.key,           // token program account
-            shared_acct.key,                  // source account
-            &state_acct_data.member_1_pubkey, // destination account
-            &pda,                             // authority account
-            &[&pda],                          // signer account
-            member1_amount,                   // amount
-        )?;
+        Ok(())
+    }
 
-        let withdraw_transfer_ix2 = spl_token::instruction::transfer(
-            token_program_acct.key,           // token program account
-            shared_acct.key,                  // source account
-            &state_acct_data.member_2_pubkey, // destination account
-            &pda,                             // authority account
-            &[&pda],                          // signer account
-            member2_amount,                   // amount
-        )?;
+    /// Checks that a proposal belongs to the distributor stored in `state_key`
+    /// and can still be approved or executed
+    fn check_open_proposal(proposal: &Proposal, state_key: &Pubkey) -> ProgramResult {
+        if proposal.distributor != *state_key {
+            return Err(RoyaltyDistributorError::ProposalMismatch.into());
+        }
+        if proposal.is_executed {
+            return Err(RoyaltyDistributorError::ProposalAlreadyExecuted.into());
+        }
+        if proposal.expires_at <= Clock::get()?.unix_timestamp {
+            return Err(RoyaltyDistributorError::ProposalExpired.into());
+        }
 
-        let withdraw_transfer_ix3 = spl_token::instruction::transfer(
-            token_program_acct.key,           // token program account
-            shared_acct.key,                  // source account
-            &state_acct_data.member_3_pubkey, // destination account
-            &pda,                             // authority account
-            &[&pda],                          // signer account
-            member3_amount,                   // amount
-        )?;
+        Ok(())
+    }
 
-        let withdraw_transfer_ix4 = spl_token::instruction::transfer(
-            token_program_acct.key,           // token program account
-            shared_acct.key,                  // source account
-            &state_acct_data.member_4_pubkey, // destination account
-            &pda,                             // authority account
-            &[&pda],                          // signer account
-            member4_amount,                   // amount
-        )?;
+    /// Checks that `signer_accts` authorize a change to the distributor: members holding at
+    /// least the approval threshold when share changes are governed by members, the admin
+    /// otherwise
+    fn check_authorizers(
+        state_acct_data: &RoyaltyDistributor,
+        signer_accts: &[AccountInfo],
+    ) -> ProgramResult {
+        let is_signer = |pubkey: &Pubkey| {
+            signer_accts
+                .iter()
+                .any(|acct| acct.is_signer && acct.key == pubkey)
+        };
+        if state_acct_data.approval_threshold > 0 {
+            let approved_shares: u32 = state_acct_data
+                .members
+                .iter()
+                .filter(|member| is_signer(&member.pubkey))
+                .map(|member| member.shares as u32)
+                .sum();
+            if approved_shares < state_acct_data.approval_threshold as u32 {
+                return Err(RoyaltyDistributorError::ApprovalThresholdNotReached.into());
+            }
+        } else if !is_signer(&state_acct_data.admin) {
+            return Err(RoyaltyDistributorError::InvalidAdmin.into());
+        }
 
-        let withdraw_transfer_ix5 = spl_token::instruction::transfer(
-            token_program_acct.key,           // token program account
-            shared_acct.key,                  // source account
-            &state_acct_data.member_5_pubkey, // destination account
-            &pda,                             // authority account
-            &[&pda],                          // signer account
-            member5_amount,                   // amount
-        )?;
+        Ok(())
+    }
 
-        let withdraw_transfer_ix6 = spl_token::instruction::transfer(
-            token_program_acct.key,           // token program account
-            shared_acct.key,                  // source account
-            &state_acct_data.member_6_pubkey, // destination account
-            &pda,                             // authority account
-            &[&pda],                          // signer account
-            member6_amount,                   // amount
+    /// Checks that `ledger_acct` holds the ledger of a mint registered with the
+    /// distributor stored in `state_key`
+    fn unpack_mint_ledger(
+        ledger_acct: &AccountInfo,
+        state_key: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<MintLedger, ProgramError> {
+        if ledger_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidLedgerAccountOwner.into());
+        }
+        let ledger =
+            MintLedger::unpack(&ledger_acct.data.borrow()).map_err(Self::ledger_account_error)?;
+        let ledger_key = Pubkey::create_program_address(
+            &[
+                MINT_LEDGER_SEED,
+                state_key.as_ref(),
+                ledger.mint.as_ref(),
+                &[ledger.bump_seed],
+            ],
+            program_id,
         )?;
+        if ledger.distributor != *state_key || *ledger_acct.key != ledger_key {
+            return Err(RoyaltyDistributorError::MintLedgerMismatch.into());
+        }
 
-        let withdraw_transfer_ix7 = spl_token::instruction::transfer(
-            token_program_acct.key,           // token program account
-            shared_acct.key,                  // source account
-            &state_acct_data.member_7_pubkey, // destination account
-            &pda,                             // authority account
-            &[&pda],                          // signer account
-            member7_amount,                   // amount
-        )?;
+        Ok(ledger)
+    }
 
-        let withdraw_transfer_ix8 = spl_token::instruction::transfer(
-            token_program_acct.key,           // token program account
-            shared_acct.key,                  // source account
-            &state_acct_data.member_8_pubkey, // destination account
-            &pda,                             // authority account
-            &[&pda],                          // signer account
-            member8_amount,                   // amount
-        )?;
+    /// Allocates the balance of `shared_acct`: the shared account of the mint of
+    /// `ledger` when settling a registered mint, of the distributor mint otherwise
+    fn settle_shared_account(
+        shared_acct: &AccountInfo,
+        ledger: Option<&mut MintLedger>,
+        state_acct_data: &mut RoyaltyDistributor,
+        pda: &Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        match ledger {
+            Some(ledger) => {
+                let shared_balance = Self::shared_token_balance(
+                    shared_acct,
+                    &ledger.shared_account,
+                    &ledger.mint,
+                    pda,
+                )?;
+                ledger.settle(state_acct_data, shared_balance)
+            }
+            None => {
+                let shared_balance =
+                    Self::shared_balance(shared_acct, state_acct_data, pda, program_id)?;
+                state_acct_data.settle(shared_balance, Clock::get()?.unix_timestamp)
+            }
+        }
+    }
 
-        msg!("Calling the token program to execute the withdraw ...");
-        // invoke_signed(
-        //     &withdraw_transfer_ix1,
-        //     &[
-        //         shared_acct.clone(),
-        //         // withdraw_acct.clone(),
-        //         pda_acct.clone(),
-        //         token_program_acct.clone(),
-        //     ],
-        //     &[&[&b"royalty_distributor"[..], &[bump_seed]]],
-        // )?;
+    /// Unpacks the ledger of every mint registered with the distributor and allocates
+    /// the balance of its shared account under the current share table
+    ///
+    /// `mint_accts` should hold, for each registered mint, its ledger account, its shared
+    /// account, then `extra_accts` more accounts; each ledger is returned along with them.
+    fn settle_registered_mints<'a, 'b>(
+        mint_accts: &'b [AccountInfo<'a>],
+        extra_accts: usize,
+        state_key: &Pubkey,
+        state_acct_data: &RoyaltyDistributor,
+        pda: &Pubkey,
+        program_id: &Pubkey,
+    ) -> Result<Vec<(MintLedger, &'b [AccountInfo<'a>])>, ProgramError> {
+        let mint_count = state_acct_data.mint_count as usize;
+        let stride = 2 + extra_accts;
+        if mint_accts.len() < mint_count * stride {
+            return Err(RoyaltyDistributorError::NotEnoughAccounts.into());
+        }
 
-        invoke_signed(
-            &withdraw_transfer_ix1,
-            &[
-                shared_acct.clone(),
-                pda_acct.clone(),
-                token_program_acct.clone(),
-            ],
-            &[&[&b"royalty_distributor"[..], &[bump_seed]]],
-        )?;
+        let mut ledgers: Vec<(MintLedger, &[AccountInfo])> = Vec::with_capacity(mint_count);
+        for accts in mint_accts.chunks_exact(stride).take(mint_count) {
+            let mut ledger = Self::unpack_mint_ledger(&accts[0], state_key, program_id)?;
+            if ledgers.iter().any(|(other, _)| other.mint == ledger.mint) {
+                return Err(RoyaltyDistributorError::MintLedgerMismatch.into());
+            }
 
-        invoke_signed(
-            &withdraw_transfer_ix2,
-            &[
-                shared_acct.clone(),
-                pda_acct.clone(),
-                token_program_acct.clone(),
-            ],
-            &[&[&b"royalty_distributor"[..], &[bump_seed]]],
-        )?;
+            let shared_balance =
+                Self::shared_token_balance(&accts[1], &ledger.shared_account, &ledger.mint, pda)?;
+            ledger.settle(state_acct_data, shared_balance)?;
+            ledgers.push((ledger, accts));
+        }
 
-        invoke_signed(
-            &withdraw_transfer_ix3,
-            &[
-                shared_acct.clone(),
-                pda_acct.clone(),
-                token_program_acct.clone(),
-            ],
-            &[&[&b"royalty_distributor"[..], &[bump_seed]]],
-        )?;
+        Ok(ledgers)
+    }
 
-        invoke_signed(
-            &withdraw_transfer_ix4,
-            &[
-                shared_acct.clone(),
-                pda_acct.clone(),
-                token_program_acct.clone(),
-            ],
-            &[&[&b"royalty_distributor"[..], &[bump_seed]]],
-        )?;
+    /// Stores the ledgers returned by `settle_registered_mints`
+    fn pack_mint_ledgers(ledgers: Vec<(MintLedger, &[AccountInfo])>) -> ProgramResult {
+        for (ledger, accts) in ledgers {
+            MintLedger::pack(ledger, &mut accts[0].data.borrow_mut())?;
+        }
 
-        invoke_signed(
-            &withdraw_transfer_ix5,
-            &[
-                shared_acct.clone(),
-                pda_acct.clone(),
-                token_program_acct.clone(),
-            ],
-            &[&[&b"royalty_distributor"[..], &[bump_seed]]],
-        )?;
+        Ok(())
+    }
 
-        invoke_signed(
-            &withdraw_transfer_ix6,
-            &[
-                shared_acct.clone(),
-                pda_acct.clone(),
-                token_program_acct.clone(),
-            ],
-            &[&[&b"royalty_distributor"[..], &[bump_seed]]],
-        )?;
+    /// Sum of the amounts paid out to members
+    fn total_amount(amounts: &[u64]) -> Result<u64, ProgramError> {
+        amounts.iter().try_fold(0u64, |total, amount| {
+            total
+                .checked_add(*amount)
+                .ok_or_else(|| RoyaltyDistributorError::ArithmeticOverflow.into())
+        })
+    }
 
-        invoke_signed(
-            &withdraw_transfer_ix7,
-            &[
-                shared_acct.clone(),
-                pda_acct.clone(),
-                token_program_acct.clone(),
-            ],
-            &[&[&b"royalty_distributor"[..], &[bump_seed]]],
-        )?;
+    /// Logs the payout of `amounts`, in member order, from a shared account holding `balance`
+    fn emit_distribution(
+        distributor: &Pubkey,
+        mint: &Pubkey,
+        balance: u64,
+        amounts: Vec<u64>,
+    ) -> ProgramResult {
+        let distributed = Self::total_amount(&amounts)?;
+        Event::Distribution {
+            distributor: *distributor,
+            mint: *mint,
+            balance,
+            amounts,
+            remainder: balance
+                .checked_sub(distributed)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?,
+            slot: Clock::get()?.slot,
+        }
+        .emit();
 
-        invoke_signed(
-            &withdraw_transfer_ix8,
-            &[
-                shared_acct.clone(),
-                pda_acct.clone(),
-                token_program_acct.clone(),
-            ],
-            &[&[&b"royalty_distributor"[..], &[bump_seed]]],
-        )?;
+        Ok(())
+    }
 
-        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+    /// Logs the governance settings and share table of the distributor
+    fn emit_config_change(
+        distributor: &Pubkey,
+        state_acct_data: &RoyaltyDistributor,
+    ) -> ProgramResult {
+        Event::ConfigChange {
+            distributor: *distributor,
+            config: ConfigSnapshot::from(state_acct_data),
+            slot: Clock::get()?.slot,
+        }
+        .emit();
 
         Ok(())
     }
+
+    /// Logs the current state of the proposal stored in `proposal_key`
+    fn emit_proposal_change(proposal_key: &Pubkey, proposal: &Proposal) -> ProgramResult {
+        Event::ProposalChange {
+            distributor: proposal.distributor,
+            proposal: *proposal_key,
+            proposer: proposal.proposer,
+            expires_at: proposal.expires_at,
+            shares: proposal.shares.clone(),
+            approvals: proposal.approvals.clone(),
+            slot: Clock::get()?.slot,
+        }
+        .emit();
+
+        Ok(())
+    }
+
+    /// Validates a share table for the current members, in member order
+    fn validate_shares(members: &[Member], shares: &[u16]) -> ProgramResult {
+        if shares.len() != members.len() {
+            return Err(RoyaltyDistributorError::MemberCountMismatch.into());
+        }
+        let members: Vec<Member> = members
+            .iter()
+            .zip(shares)
+            .map(|(member, &member_shares)| Member::new(member.pubkey, member_shares))
+            .collect();
+
+        Self::validate_members(&members)
+    }
+
+    /// Rewrites the share table of every member, in member order, once the
+    /// timelock elapses, or right away if there is no timelock
+    fn queue_shares(
+        state_acct_data: &mut RoyaltyDistributor,
+        shares: Vec<u16>,
+        now: i64,
+    ) -> ProgramResult {
+        if state_acct_data.timelock == 0 {
+            return Self::set_shares(state_acct_data, shares);
+        }
+        if state_acct_data.has_pending_shares() {
+            return Err(RoyaltyDistributorError::PendingSharesUpdate.into());
+        }
+
+        Self::validate_shares(&state_acct_data.members, &shares)?;
+        for (member, member_shares) in state_acct_data.members.iter_mut().zip(shares) {
+            member.pending_shares = member_shares;
+            member.contested = false;
+        }
+        state_acct_data.pending_effective_at = now
+            .checked_add(state_acct_data.timelock)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    /// Rewrites the share table of every member, in member order
+    fn set_shares(state_acct_data: &mut RoyaltyDistributor, shares: Vec<u16>) -> ProgramResult {
+        if shares.len() != state_acct_data.members.len() {
+            return Err(RoyaltyDistributorError::MemberCountMismatch.into());
+        }
+        for (member, member_shares) in state_acct_data.members.iter_mut().zip(shares) {
+            member.shares = member_shares;
+        }
+
+        Self::validate_members(&state_acct_data.members)
+    }
+
+    fn process_init_royalty_distributor(
+        accounts: &[AccountInfo],
+        config: DistributorConfig,
+        members: Vec<Member>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Initializer account
+        let init_acct = next_account_info(account_info_iter)?;
+        if !init_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] Shared account, or PDA vault for native distributors
+        let shared_acct = next_account_info(account_info_iter)?;
+
+        // [Account 2] State account
+        // Should be owned by this program and large enough to hold every member
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+        if state_acct.data_len() < RoyaltyDistributor::get_packed_len(members.len()) {
+            return Err(RoyaltyDistributorError::StateAccountTooSmall.into());
+        }
+
+        // [Account 3] Rent sysvar account
+        let rent_acct = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+        if !rent_acct.is_exempt(state_acct.lamports(), state_acct.data_len()) {
+            return Err(RoyaltyDistributorError::NotRentExempt.into());
+        }
+
+        // Ensure that state account is not initialized yet
+        let mut state_acct_data = RoyaltyDistributor::unpack_unchecked(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        if state_acct_data.is_initialized() {
+            return Err(RoyaltyDistributorError::AlreadyInitialized.into());
+        }
+
+        // Get a Program Derived Address (PDA) specific to this distributor
+        let (pda, bump_seed) = find_authority_address(state_acct.key, program_id);
+
+        // [Account 4] Token program account, or system program account for native distributors
+        let program_acct = next_account_info(account_info_iter)?;
+        let mint = if config.is_native {
+            // The vault should be the PDA, created below
+            if *shared_acct.key != pda {
+                return Err(RoyaltyDistributorError::InvalidAuthority.into());
+            }
+            if shared_acct.owner == program_id {
+                return Err(RoyaltyDistributorError::AlreadyInitialized.into());
+            }
+            if *program_acct.key != system_program::id() {
+                return Err(RoyaltyDistributorError::InvalidSystemProgram.into());
+            }
+            Pubkey::default()
+        } else {
+            // The shared account should be internally owned by token program
+            if *shared_acct.owner != spl_token::id() {
+                return Err(RoyaltyDistributorError::SharedAccountNotTokenAccount.into());
+            }
+            if *program_acct.key != spl_token::id() {
+                return Err(RoyaltyDistributorError::InvalidTokenProgram.into());
+            }
+            let shared_acct_data = TokenAccount::unpack(&shared_acct.data.borrow())
+                .map_err(|_| RoyaltyDistributorError::SharedAccountNotTokenAccount)?;
+            Self::check_shared_account_authorities(&shared_acct_data, &pda)?;
+            shared_acct_data.mint
+        };
+
+        Self::validate_members(&members)?;
+
+        // Remainder should go to an existing member
+        if let RemainderPolicy::Member(member_index) = config.remainder_policy {
+            if member_index as usize >= members.len() {
+                return Err(RoyaltyDistributorError::InvalidRemainderMember.into());
+            }
+        }
+
+        // Cranker should be set
+        if config.distribution_policy == DistributionPolicy::Cranker(Pubkey::default()) {
+            return Err(RoyaltyDistributorError::InvalidCranker.into());
+        }
+
+        // Approval threshold should not exceed the total shares
+        if config.approval_threshold > TOTAL_SHARES {
+            return Err(RoyaltyDistributorError::InvalidApprovalThreshold.into());
+        }
+
+        // Timelock should not be negative
+        if config.timelock < 0 {
+            return Err(RoyaltyDistributorError::InvalidTimelock.into());
+        }
+
+        // Receipt retention period should not be negative
+        if let ReceiptPolicy::Retain(retention) = config.receipt_policy {
+            if retention < 0 {
+                return Err(RoyaltyDistributorError::InvalidReceiptRetention.into());
+            }
+        }
+
+        // Populate data fields on state account
+        state_acct_data.is_initialized = true;
+        state_acct_data.bump_seed = bump_seed;
+        state_acct_data.is_native = config.is_native;
+        state_acct_data.shared_account = *shared_acct.key;
+        state_acct_data.mint = mint;
+        state_acct_data.remainder_policy = config.remainder_policy;
+        state_acct_data.distribution_policy = config.distribution_policy;
+        state_acct_data.receipt_policy = config.receipt_policy;
+        state_acct_data.admin = config.admin;
+        state_acct_data.approval_threshold = config.approval_threshold;
+        state_acct_data.timelock = config.timelock;
+        state_acct_data.members = members;
+
+        Event::Init {
+            distributor: *state_acct.key,
+            mint,
+            config: ConfigSnapshot::from(&state_acct_data),
+            slot: Clock::get()?.slot,
+        }
+        .emit();
+
+        // Store information state account
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+
+        if config.is_native {
+            // Create the vault: a rent-exempt account without data, owned by this program
+            // Lamports sent to the vault address beforehand are kept and shared like royalties
+            msg!("Calling the system program to create the vault ...");
+            return Self::create_pda_account(
+                init_acct,
+                shared_acct,
+                program_acct,
+                0,
+                &[AUTHORITY_SEED, state_acct.key.as_ref(), &[bump_seed]],
+                program_id,
+            );
+        }
+
+        // Create the 'change owner' instruction
+        let owner_change_ix = spl_token::instruction::set_authority(
+            program_acct.key, // token program id
+            shared_acct.key,  // account whose authority we would like to change
+            Some(&pda),       // account that should be the new authority of the account
+            AccountOwner,     // type of authority change
+            init_acct.key,    // current account owner
+            &[init_acct.key], // public keys signing the cross program invocation (CPI)
+        )?;
+
+        // Cross-Program Invocation (CPI)
+        msg!("Calling the token program to transfer shared account ownership ...");
+        invoke(
+            &owner_change_ix,
+            &[shared_acct.clone(), init_acct.clone(), program_acct.clone()],
+        )?;
+
+        Ok(())
+    }
+
+    fn process_init_from_metadata(
+        accounts: &[AccountInfo],
+        config: DistributorConfig,
+        require_verified_creators: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // [Account 5] Metadata account
+        // Should be owned by the Token Metadata program
+        let metadata_acct = accounts
+            .get(5)
+            .ok_or(RoyaltyDistributorError::NotEnoughAccounts)?;
+        if *metadata_acct.owner != metadata::id() {
+            return Err(RoyaltyDistributorError::InvalidMetadataAccountOwner.into());
+        }
+        let creators = metadata::unpack_creators(&metadata_acct.data.borrow())?;
+        let members = metadata::creator_members(&creators, require_verified_creators)?;
+
+        // [Accounts 0..4] Same accounts as InitRoyaltyDistributor
+        Self::process_init_royalty_distributor(accounts, config, members, program_id)
+    }
+
+    fn process_withdraw(
+        accounts: &[AccountInfo],
+        mint: Option<Pubkey>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Account executing the withdraw
+        let init_acct = next_account_info(account_info_iter)?;
+        if !init_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        let authority_seeds: &[&[u8]] = &[
+            AUTHORITY_SEED,
+            state_acct.key.as_ref(),
+            &[state_acct_data.bump_seed],
+        ];
+        let pda = Pubkey::create_program_address(authority_seeds, program_id)?;
+
+        // The signer should be allowed to distribute
+        let allowed = match state_acct_data.distribution_policy {
+            DistributionPolicy::Permissionless => true,
+            DistributionPolicy::MembersOnly => state_acct_data
+                .members
+                .iter()
+                .any(|member| member.pubkey == *init_acct.key),
+            DistributionPolicy::Cranker(cranker) => cranker == *init_acct.key,
+        };
+        if !allowed {
+            return Err(RoyaltyDistributorError::DistributionNotAllowed.into());
+        }
+
+        // [Account 2] Shared account
+        // Should be the shared account of the mint to settle, owned by the PDA
+        let shared_acct = next_account_info(account_info_iter)?;
+
+        // [Account 3] Token program account
+        let token_program_acct = next_account_info(account_info_iter)?;
+        if *token_program_acct.key != spl_token::id() {
+            return Err(RoyaltyDistributorError::InvalidTokenProgram.into());
+        }
+
+        // [Account 4] The PDA account
+        // Should be the PDA of this distributor
+        let pda_acct = next_account_info(account_info_iter)?;
+        if *pda_acct.key != pda {
+            return Err(RoyaltyDistributorError::InvalidAuthority.into());
+        }
+
+        // [Account 5] Ledger account, only to settle a registered mint
+        let mut ledger = match mint {
+            Some(mint) if mint != state_acct_data.mint => {
+                let ledger_acct = next_account_info(account_info_iter)?;
+                let ledger = Self::unpack_mint_ledger(ledger_acct, state_acct.key, program_id)?;
+                if ledger.mint != mint {
+                    return Err(RoyaltyDistributorError::MintLedgerMismatch.into());
+                }
+                Some((ledger, ledger_acct))
+            }
+            _ => None,
+        };
+
+        // [Accounts 5.. or 6..] Destination token accounts, one per member in member order
+        // Should hold the mint to settle and be owned by the corresponding member,
+        // or be the member wallet for native distributors
+        let remaining_accts = account_info_iter.as_slice();
+        if remaining_accts.len() < state_acct_data.members.len() {
+            return Err(RoyaltyDistributorError::NotEnoughAccounts.into());
+        }
+        let (destination_accts, remaining_accts) =
+            remaining_accts.split_at(state_acct_data.members.len());
+        for (member, destination_acct) in state_acct_data.members.iter().zip(destination_accts) {
+            match &ledger {
+                Some((ledger, _)) => Self::check_destination_token_account(
+                    destination_acct,
+                    &member.payee(program_id),
+                    &ledger.mint,
+                )?,
+                None => Self::check_destination_account(
+                    destination_acct,
+                    &member.payee(program_id),
+                    &state_acct_data,
+                )?,
+            }
+        }
+
+        // [Accounts after the destinations] State account of each nested distributor
+        // in member order, or its ledger account of the mint when settling a registered mint
+        // The destination of a nested distributor should be the shared account it records
+        let nested_count = state_acct_data.nested_member_count();
+        if remaining_accts.len() < nested_count {
+            return Err(RoyaltyDistributorError::NotEnoughAccounts.into());
+        }
+        let (nested_accts, receipt_accts) = remaining_accts.split_at(nested_count);
+        Self::check_nested_destinations(
+            &state_acct_data.members,
+            destination_accts,
+            nested_accts,
+            ledger.as_ref().map(|(ledger, _)| &ledger.mint),
+            program_id,
+        )?;
+
+        // Share table the balance is allocated under, before any pending one takes effect
+        let shares: Vec<u16> = state_acct_data
+            .members
+            .iter()
+            .map(|member| member.shares)
+            .collect();
+
+        // Allocate the shared account balance and release the pending amount of each member
+        Self::settle_shared_account(
+            shared_acct,
+            ledger.as_mut().map(|(ledger, _)| ledger),
+            &mut state_acct_data,
+            &pda,
+            program_id,
+        )?;
+
+        let (mint, shared_balance) = match &ledger {
+            Some((ledger, _)) => (
+                ledger.mint,
+                ledger
+                    .total_received
+                    .checked_sub(ledger.total_released)
+                    .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?,
+            ),
+            None => (
+                state_acct_data.mint,
+                state_acct_data
+                    .total_received
+                    .checked_sub(state_acct_data.total_released)
+                    .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?,
+            ),
+        };
+
+        msg!("Calling the token program to execute the withdraw ...");
+        let is_native = state_acct_data.is_native && ledger.is_none();
+        let mut member_amounts = Vec::with_capacity(state_acct_data.members.len());
+        for (member_index, destination_acct) in destination_accts.iter().enumerate() {
+            let member_amount = match &mut ledger {
+                Some((ledger, _)) => ledger.release(member_index)?,
+                None => state_acct_data.release(member_index)?,
+            };
+            member_amounts.push(member_amount);
+            if member_amount == 0 {
+                continue;
+            }
+
+            Self::transfer_from_shared(
+                is_native,
+                token_program_acct,
+                shared_acct,
+                destination_acct,
+                pda_acct,
+                authority_seeds,
+                member_amount,
+            )?;
+        }
+
+        // Record the payout in the history of the shared account
+        let clock = Clock::get()?;
+        let distributed = Self::total_amount(&member_amounts)?;
+        if distributed > 0 {
+            match &mut ledger {
+                Some((ledger, _)) => {
+                    ledger.record_distribution(distributed, clock.slot, clock.unix_timestamp)?
+                }
+                None => state_acct_data.record_distribution(
+                    distributed,
+                    clock.slot,
+                    clock.unix_timestamp,
+                )?,
+            }
+        }
+
+        // [Accounts after the nested distributor accounts] Receipt account and system
+        // program account, only when the distributor retains receipts
+        if let ReceiptPolicy::Retain(retention) = state_acct_data.receipt_policy {
+            let receipt = Receipt {
+                is_initialized: true,
+                bump_seed: 0,
+                distributor: *state_acct.key,
+                round: state_acct_data.receipt_count,
+                payer: *init_acct.key,
+                mint,
+                balance: shared_balance,
+                created_at: clock.unix_timestamp,
+                retain_until: clock
+                    .unix_timestamp
+                    .checked_add(retention)
+                    .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?,
+                entries: state_acct_data
+                    .members
+                    .iter()
+                    .zip(shares)
+                    .zip(member_amounts.iter())
+                    .map(|((member, shares), amount)| ReceiptEntry {
+                        pubkey: member.pubkey,
+                        shares,
+                        amount: *amount,
+                    })
+                    .collect(),
+            };
+            Self::create_receipt(init_acct, receipt_accts, receipt, program_id)?;
+            state_acct_data.receipt_count = state_acct_data
+                .receipt_count
+                .checked_add(1)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+        }
+
+        Self::emit_distribution(state_acct.key, &mint, shared_balance, member_amounts)?;
+
+        if let Some((ledger, ledger_acct)) = ledger {
+            MintLedger::pack(ledger, &mut ledger_acct.data.borrow_mut())?;
+        }
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    /// Creates `pda_acct`, the program address of `pda_seeds`, with `space` bytes owned
+    /// by this program, funded by `payer_acct` up to the rent-exempt minimum
+    ///
+    /// Anyone may send lamports to a program address before it is created, which makes
+    /// the system program refuse to create it: such an account is topped up, allocated
+    /// and assigned instead.
+    fn create_pda_account<'a>(
+        payer_acct: &AccountInfo<'a>,
+        pda_acct: &AccountInfo<'a>,
+        system_program_acct: &AccountInfo<'a>,
+        space: usize,
+        pda_seeds: &[&[u8]],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let rent_exempt_lamports = Rent::get()?.minimum_balance(space);
+        let funding_accts = [
+            payer_acct.clone(),
+            pda_acct.clone(),
+            system_program_acct.clone(),
+        ];
+
+        if pda_acct.lamports() == 0 {
+            let create_ix = system_instruction::create_account(
+                payer_acct.key,       // funding account
+                pda_acct.key,         // new account
+                rent_exempt_lamports, // lamports
+                space as u64,         // space
+                program_id,           // owner
+            );
+            return invoke_signed(&create_ix, &funding_accts, &[pda_seeds]);
+        }
+
+        let missing_lamports = rent_exempt_lamports.saturating_sub(pda_acct.lamports());
+        if missing_lamports > 0 {
+            let transfer_ix =
+                system_instruction::transfer(payer_acct.key, pda_acct.key, missing_lamports);
+            invoke(&transfer_ix, &funding_accts)?;
+        }
+
+        let pda_accts = [pda_acct.clone(), system_program_acct.clone()];
+        let allocate_ix = system_instruction::allocate(pda_acct.key, space as u64);
+        invoke_signed(&allocate_ix, &pda_accts, &[pda_seeds])?;
+        let assign_ix = system_instruction::assign(pda_acct.key, program_id);
+        invoke_signed(&assign_ix, &pda_accts, &[pda_seeds])
+    }
+
+    /// Creates the account of `receipt`, funded by `payer_acct`, and stores the receipt
+    ///
+    /// `receipt_accts` should hold the receipt account, then the system program account.
+    fn create_receipt<'a>(
+        payer_acct: &AccountInfo<'a>,
+        receipt_accts: &[AccountInfo<'a>],
+        mut receipt: Receipt,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        let account_info_iter = &mut receipt_accts.iter();
+
+        // Receipt account
+        // Should be the PDA derived from the state account key and the round, created below
+        let receipt_acct = next_account_info(account_info_iter)?;
+        let (receipt_key, receipt_bump_seed) =
+            find_receipt_address(&receipt.distributor, receipt.round, program_id);
+        if *receipt_acct.key != receipt_key {
+            return Err(RoyaltyDistributorError::ReceiptMismatch.into());
+        }
+
+        // System program account
+        let system_program_acct = next_account_info(account_info_iter)?;
+        if *system_program_acct.key != system_program::id() {
+            return Err(RoyaltyDistributorError::InvalidSystemProgram.into());
+        }
+
+        msg!("Calling the system program to create the receipt ...");
+        Self::create_pda_account(
+            payer_acct,
+            receipt_acct,
+            system_program_acct,
+            Receipt::get_packed_len(receipt.entries.len()),
+            &[
+                RECEIPT_SEED,
+                receipt.distributor.as_ref(),
+                &receipt.round.to_le_bytes(),
+                &[receipt_bump_seed],
+            ],
+            program_id,
+        )?;
+
+        receipt.bump_seed = receipt_bump_seed;
+        Receipt::pack(receipt, &mut receipt_acct.data.borrow_mut())
+    }
+
+    fn process_claim(
+        accounts: &[AccountInfo],
+        mint: Option<Pubkey>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Account of the member claiming
+        let member_acct = next_account_info(account_info_iter)?;
+        if !member_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        let authority_seeds: &[&[u8]] = &[
+            AUTHORITY_SEED,
+            state_acct.key.as_ref(),
+            &[state_acct_data.bump_seed],
+        ];
+        let pda = Pubkey::create_program_address(authority_seeds, program_id)?;
+
+        // The signer should be a member
+        let member_index = state_acct_data
+            .members
+            .iter()
+            .position(|member| member.pubkey == *member_acct.key)
+            .ok_or(RoyaltyDistributorError::NotAMember)?;
+
+        // Payouts of a nested distributor should only go into its recorded shared account,
+        // through Withdraw or DistributeTree
+        if state_acct_data.members[member_index].is_distributor {
+            return Err(RoyaltyDistributorError::MemberIsDistributor.into());
+        }
+        let payee = state_acct_data.members[member_index].payee(program_id);
+
+        // [Account 2] Shared account
+        // Should be the shared account of the mint to settle, owned by the PDA
+        let shared_acct = next_account_info(account_info_iter)?;
+
+        // [Account 3] Destination token account of the member
+        // Should hold the mint to settle and be owned by the member,
+        // or be the member wallet for native distributors
+        let destination_acct = next_account_info(account_info_iter)?;
+
+        // [Account 4] Token program account
+        let token_program_acct = next_account_info(account_info_iter)?;
+        if *token_program_acct.key != spl_token::id() {
+            return Err(RoyaltyDistributorError::InvalidTokenProgram.into());
+        }
+
+        // [Account 5] The PDA account
+        // Should be the PDA of this distributor
+        let pda_acct = next_account_info(account_info_iter)?;
+        if *pda_acct.key != pda {
+            return Err(RoyaltyDistributorError::InvalidAuthority.into());
+        }
+
+        // [Account 6] Ledger account, only to settle a registered mint
+        let mut ledger = match mint {
+            Some(mint) if mint != state_acct_data.mint => {
+                let ledger_acct = next_account_info(account_info_iter)?;
+                let ledger = Self::unpack_mint_ledger(ledger_acct, state_acct.key, program_id)?;
+                if ledger.mint != mint {
+                    return Err(RoyaltyDistributorError::MintLedgerMismatch.into());
+                }
+                Self::check_destination_token_account(destination_acct, &payee, &ledger.mint)?;
+                Some((ledger, ledger_acct))
+            }
+            _ => {
+                Self::check_destination_account(destination_acct, &payee, &state_acct_data)?;
+                None
+            }
+        };
+
+        // Allocate the shared account balance and release the pending amount of the member
+        Self::settle_shared_account(
+            shared_acct,
+            ledger.as_mut().map(|(ledger, _)| ledger),
+            &mut state_acct_data,
+            &pda,
+            program_id,
+        )?;
+        let member_amount = match &mut ledger {
+            Some((ledger, _)) => ledger.release(member_index)?,
+            None => state_acct_data.release(member_index)?,
+        };
+
+        if member_amount > 0 {
+            msg!("Calling the token program to execute the claim ...");
+            Self::transfer_from_shared(
+                state_acct_data.is_native && ledger.is_none(),
+                token_program_acct,
+                shared_acct,
+                destination_acct,
+                pda_acct,
+                authority_seeds,
+                member_amount,
+            )?;
+
+            // Record the payout in the history of the shared account
+            let clock = Clock::get()?;
+            let mint = match &mut ledger {
+                Some((ledger, _)) => {
+                    ledger.record_distribution(member_amount, clock.slot, clock.unix_timestamp)?;
+                    ledger.mint
+                }
+                None => {
+                    state_acct_data.record_distribution(
+                        member_amount,
+                        clock.slot,
+                        clock.unix_timestamp,
+                    )?;
+                    state_acct_data.mint
+                }
+            };
+            Event::Claim {
+                distributor: *state_acct.key,
+                mint,
+                member: *member_acct.key,
+                amount: member_amount,
+                slot: clock.slot,
+            }
+            .emit();
+        }
+
+        match ledger {
+            Some((ledger, ledger_acct)) => {
+                MintLedger::pack(ledger, &mut ledger_acct.data.borrow_mut())?
+            }
+            None => RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?,
+        }
+
+        Ok(())
+    }
+
+    fn process_update_shares(
+        accounts: &[AccountInfo],
+        shares: Vec<u16>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Admin account
+        let admin_acct = next_account_info(account_info_iter)?;
+        if !admin_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        Self::check_admin(&state_acct_data, admin_acct)?;
+        if state_acct_data.approval_threshold > 0 {
+            return Err(RoyaltyDistributorError::GovernedByMembers.into());
+        }
+        let pda = Pubkey::create_program_address(
+            &[
+                AUTHORITY_SEED,
+                state_acct.key.as_ref(),
+                &[state_acct_data.bump_seed],
+            ],
+            program_id,
+        )?;
+
+        // [Account 2] Shared account
+        // Should be the shared account of this distributor, owned by its PDA
+        let shared_acct = next_account_info(account_info_iter)?;
+        let shared_balance = Self::shared_balance(shared_acct, &state_acct_data, &pda, program_id)?;
+
+        // [Accounts 3..] Ledger account and shared account of each registered mint
+        let mint_accts = account_info_iter.as_slice();
+
+        // Allocate the shared account balances under the old share table
+        let now = Clock::get()?.unix_timestamp;
+        state_acct_data.settle(shared_balance, now)?;
+        let ledgers = Self::settle_registered_mints(
+            mint_accts,
+            0,
+            state_acct.key,
+            &state_acct_data,
+            &pda,
+            program_id,
+        )?;
+
+        // Rewrite the share table, once the timelock elapses
+        Self::queue_shares(&mut state_acct_data, shares, now)?;
+        Self::emit_config_change(state_acct.key, &state_acct_data)?;
+
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+        Self::pack_mint_ledgers(ledgers)?;
+
+        Ok(())
+    }
+
+    fn process_add_member(
+        accounts: &[AccountInfo],
+        pubkey: Pubkey,
+        shares: Vec<u16>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Admin account
+        let admin_acct = next_account_info(account_info_iter)?;
+        if !admin_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        Self::check_admin(&state_acct_data, admin_acct)?;
+        if state_acct_data.approval_threshold > 0 {
+            return Err(RoyaltyDistributorError::GovernedByMembers.into());
+        }
+        let pda = Pubkey::create_program_address(
+            &[
+                AUTHORITY_SEED,
+                state_acct.key.as_ref(),
+                &[state_acct_data.bump_seed],
+            ],
+            program_id,
+        )?;
+
+        // State account should be large enough to hold one more member
+        if state_acct.data_len()
+            < RoyaltyDistributor::get_packed_len(state_acct_data.members.len() + 1)
+        {
+            return Err(RoyaltyDistributorError::StateAccountTooSmall.into());
+        }
+
+        // [Account 2] Shared account
+        // Should be the shared account of this distributor, owned by its PDA
+        let shared_acct = next_account_info(account_info_iter)?;
+        let shared_balance = Self::shared_balance(shared_acct, &state_acct_data, &pda, program_id)?;
+
+        // [Accounts 3..] Ledger account and shared account of each registered mint
+        let mint_accts = account_info_iter.as_slice();
+
+        // Allocate the shared account balances under the old share table
+        state_acct_data.settle(shared_balance, Clock::get()?.unix_timestamp)?;
+        if state_acct_data.has_pending_shares() {
+            return Err(RoyaltyDistributorError::PendingSharesUpdate.into());
+        }
+        let mut ledgers = Self::settle_registered_mints(
+            mint_accts,
+            0,
+            state_acct.key,
+            &state_acct_data,
+            &pda,
+            program_id,
+        )?;
+
+        // Append the member and rewrite the share table
+        state_acct_data.members.push(Member::new(pubkey, 0));
+        Self::set_shares(&mut state_acct_data, shares)?;
+        for (ledger, _) in ledgers.iter_mut() {
+            ledger.entries.push(LedgerEntry::default());
+        }
+        Self::emit_config_change(state_acct.key, &state_acct_data)?;
+
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+        Self::pack_mint_ledgers(ledgers)?;
+
+        Ok(())
+    }
+
+    fn process_remove_member(
+        accounts: &[AccountInfo],
+        member_index: u16,
+        shares: Vec<u16>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Admin account
+        let admin_acct = next_account_info(account_info_iter)?;
+        if !admin_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        Self::check_admin(&state_acct_data, admin_acct)?;
+        if state_acct_data.approval_threshold > 0 {
+            return Err(RoyaltyDistributorError::GovernedByMembers.into());
+        }
+        let authority_seeds: &[&[u8]] = &[
+            AUTHORITY_SEED,
+            state_acct.key.as_ref(),
+            &[state_acct_data.bump_seed],
+        ];
+        let pda = Pubkey::create_program_address(authority_seeds, program_id)?;
+
+        let member = state_acct_data
+            .members
+            .get(member_index as usize)
+            .ok_or(RoyaltyDistributorError::NotAMember)?;
+        let (member_pubkey, member_is_distributor) = (member.pubkey, member.is_distributor);
+        let payee = member.payee(program_id);
+
+        // [Account 2] Shared account
+        // Should be the shared account of this distributor, owned by its PDA
+        let shared_acct = next_account_info(account_info_iter)?;
+        let shared_balance = Self::shared_balance(shared_acct, &state_acct_data, &pda, program_id)?;
+
+        // [Account 3] Destination token account of the removed member
+        // Should hold the distributor mint and be owned by the removed member,
+        // or be the member wallet for native distributors
+        let destination_acct = next_account_info(account_info_iter)?;
+        Self::check_destination_account(destination_acct, &payee, &state_acct_data)?;
+
+        // [Account 4] Token program account
+        let token_program_acct = next_account_info(account_info_iter)?;
+        if *token_program_acct.key != spl_token::id() {
+            return Err(RoyaltyDistributorError::InvalidTokenProgram.into());
+        }
+
+        // [Account 5] The PDA account
+        // Should be the PDA of this distributor
+        let pda_acct = next_account_info(account_info_iter)?;
+        if *pda_acct.key != pda {
+            return Err(RoyaltyDistributorError::InvalidAuthority.into());
+        }
+
+        // [Account 6] State account of the removed member, only when it is a nested distributor
+        // The destination should then be the shared account it records
+        // Its nested flag is cleared along with the member
+        let nested_acct = if member_is_distributor {
+            let nested_acct = next_account_info(account_info_iter)?;
+            Self::check_nested_destination(
+                &member_pubkey,
+                destination_acct,
+                nested_acct,
+                None,
+                program_id,
+            )?;
+            Some(nested_acct)
+        } else {
+            None
+        };
+
+        // [Accounts 7..] State account of each other nested distributor in member order,
+        // only when the removed member is a nested distributor
+        // The nesting depth of this distributor is recomputed from the ones that remain
+        let other_nested_accts = if member_is_distributor {
+            let other_nested_count = state_acct_data.nested_member_count() - 1;
+            let remaining_accts = account_info_iter.as_slice();
+            if remaining_accts.len() < other_nested_count {
+                return Err(RoyaltyDistributorError::NotEnoughAccounts.into());
+            }
+            let (other_nested_accts, remaining_accts) =
+                remaining_accts.split_at(other_nested_count);
+            *account_info_iter = remaining_accts.iter();
+            other_nested_accts
+        } else {
+            &[]
+        };
+
+        // Allocate the shared account balance under the old share table
+        // and release the pending amount of the removed member
+        let clock = Clock::get()?;
+        state_acct_data.settle(shared_balance, clock.unix_timestamp)?;
+        if state_acct_data.has_pending_shares() {
+            return Err(RoyaltyDistributorError::PendingSharesUpdate.into());
+        }
+        let member_amount = state_acct_data.release(member_index as usize)?;
+
+        if member_amount > 0 {
+            msg!("Calling the token program to pay the removed member ...");
+            Self::transfer_from_shared(
+                state_acct_data.is_native,
+                token_program_acct,
+                shared_acct,
+                destination_acct,
+                pda_acct,
+                authority_seeds,
+                member_amount,
+            )?;
+            state_acct_data.record_distribution(member_amount, clock.slot, clock.unix_timestamp)?;
+            Event::Claim {
+                distributor: *state_acct.key,
+                mint: state_acct_data.mint,
+                member: member_pubkey,
+                amount: member_amount,
+                slot: clock.slot,
+            }
+            .emit();
+        }
+
+        // [Accounts 6.., or after the nested distributors] Ledger account, shared account and
+        // destination token account of the removed member, for each registered mint, followed
+        // by the ledger account of the removed member when it is a nested distributor
+        // Their balances are allocated under the old share table as well
+        let mut ledgers = Self::settle_registered_mints(
+            account_info_iter.as_slice(),
+            1 + member_is_distributor as usize,
+            state_acct.key,
+            &state_acct_data,
+            &pda,
+            program_id,
+        )?;
+        for (ledger, accts) in ledgers.iter_mut() {
+            Self::check_destination_token_account(&accts[2], &payee, &ledger.mint)?;
+            if member_is_distributor {
+                Self::check_nested_destination(
+                    &member_pubkey,
+                    &accts[2],
+                    &accts[3],
+                    Some(&ledger.mint),
+                    program_id,
+                )?;
+            }
+            let member_amount = ledger.release(member_index as usize)?;
+            if member_amount > 0 {
+                Self::transfer_from_shared(
+                    false,
+                    token_program_acct,
+                    &accts[1],
+                    &accts[2],
+                    pda_acct,
+                    authority_seeds,
+                    member_amount,
+                )?;
+                ledger.record_distribution(member_amount, clock.slot, clock.unix_timestamp)?;
+                Event::Claim {
+                    distributor: *state_acct.key,
+                    mint: ledger.mint,
+                    member: member_pubkey,
+                    amount: member_amount,
+                    slot: clock.slot,
+                }
+                .emit();
+            }
+            ledger.remove_member(member_index as usize)?;
+        }
+
+        // Remove the member and rewrite the share table
+        state_acct_data.remove_member(member_index as usize)?;
+        Self::set_shares(&mut state_acct_data, shares)?;
+
+        // The removed distributor may have been the deepest branch below this one
+        if member_is_distributor {
+            let nested_members = state_acct_data
+                .members
+                .iter()
+                .filter(|member| member.is_distributor);
+            let mut nesting_depth = 0;
+            for (member, other_nested_acct) in nested_members.zip(other_nested_accts) {
+                let other_nested_acct_data =
+                    Self::unpack_nested_distributor(other_nested_acct, &member.pubkey, program_id)?;
+                nesting_depth = nesting_depth.max(other_nested_acct_data.nesting_depth + 1);
+            }
+            state_acct_data.nesting_depth = nesting_depth;
+        }
+        Self::emit_config_change(state_acct.key, &state_acct_data)?;
+
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+        Self::pack_mint_ledgers(ledgers)?;
+        if let Some(nested_acct) = nested_acct {
+            Self::unflag_nested_distributor(nested_acct)?;
+        }
+
+        Ok(())
+    }
+
+    fn process_transfer_membership(
+        accounts: &[AccountInfo],
+        require_new_member_signature: bool,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Account of the current member
+        let member_acct = next_account_info(account_info_iter)?;
+        if !member_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+
+        // The signer should be a member
+        let member_index = state_acct_data
+            .members
+            .iter()
+            .position(|member| member.pubkey == *member_acct.key)
+            .ok_or(RoyaltyDistributorError::NotAMember)?;
+
+        // A nested distributor keeps its slot
+        if state_acct_data.members[member_index].is_distributor {
+            return Err(RoyaltyDistributorError::MemberIsDistributor.into());
+        }
+
+        // [Account 2] Account of the new member
+        // Should sign if required, be set and not already be a member
+        let new_member_acct = next_account_info(account_info_iter)?;
+        if require_new_member_signature && !new_member_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+        if *new_member_acct.key == Pubkey::default() {
+            return Err(RoyaltyDistributorError::InvalidMemberPubkey.into());
+        }
+        if state_acct_data
+            .members
+            .iter()
+            .any(|member| member.pubkey == *new_member_acct.key)
+        {
+            return Err(RoyaltyDistributorError::DuplicateMember.into());
+        }
+
+        // Reassign the slot, shares and pending amount follow
+        state_acct_data.members[member_index].pubkey = *new_member_acct.key;
+        Self::emit_config_change(state_acct.key, &state_acct_data)?;
+
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_propose_shares(
+        accounts: &[AccountInfo],
+        expires_at: i64,
+        shares: Vec<u16>,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Account of the member making the proposal
+        let member_acct = next_account_info(account_info_iter)?;
+        if !member_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        if state_acct_data.approval_threshold == 0 {
+            return Err(RoyaltyDistributorError::NotGovernedByMembers.into());
+        }
+
+        // The signer should be a member
+        if !state_acct_data
+            .members
+            .iter()
+            .any(|member| member.pubkey == *member_acct.key)
+        {
+            return Err(RoyaltyDistributorError::NotAMember.into());
+        }
+
+        // [Account 2] Proposal account
+        // Should be owned by this program and large enough for every member
+        let proposal_acct = next_account_info(account_info_iter)?;
+        if proposal_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidProposalAccountOwner.into());
+        }
+        if proposal_acct.data_len() < Proposal::get_packed_len(state_acct_data.members.len()) {
+            return Err(RoyaltyDistributorError::ProposalAccountTooSmall.into());
+        }
+
+        // [Account 3] Rent sysvar account
+        let rent_acct = &Rent::from_account_info(next_account_info(account_info_iter)?)?;
+        if !rent_acct.is_exempt(proposal_acct.lamports(), proposal_acct.data_len()) {
+            return Err(RoyaltyDistributorError::NotRentExempt.into());
+        }
+
+        // Ensure that proposal account is not initialized yet
+        let mut proposal_acct_data = Proposal::unpack_unchecked(&proposal_acct.data.borrow())
+            .map_err(Self::proposal_account_error)?;
+        if proposal_acct_data.is_initialized() {
+            return Err(RoyaltyDistributorError::AlreadyInitialized.into());
+        }
+
+        // Proposal should not be expired already
+        if expires_at <= Clock::get()?.unix_timestamp {
+            return Err(RoyaltyDistributorError::ProposalExpired.into());
+        }
+
+        // Proposed share table should be valid for the current members
+        Self::validate_shares(&state_acct_data.members, &shares)?;
+
+        // Populate data fields on proposal account, approved by the proposer
+        proposal_acct_data.is_initialized = true;
+        proposal_acct_data.distributor = *state_acct.key;
+        proposal_acct_data.proposer = *member_acct.key;
+        proposal_acct_data.expires_at = expires_at;
+        proposal_acct_data.is_executed = false;
+        proposal_acct_data.shares = shares;
+        proposal_acct_data.approvals = vec![*member_acct.key];
+        Self::emit_proposal_change(proposal_acct.key, &proposal_acct_data)?;
+
+        Proposal::pack(proposal_acct_data, &mut proposal_acct.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_approve_proposal(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Account of the member approving
+        let member_acct = next_account_info(account_info_iter)?;
+        if !member_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+
+        // The signer should be a member
+        if !state_acct_data
+            .members
+            .iter()
+            .any(|member| member.pubkey == *member_acct.key)
+        {
+            return Err(RoyaltyDistributorError::NotAMember.into());
+        }
+
+        // [Account 2] Proposal account
+        // Should be an open proposal of this distributor
+        let proposal_acct = next_account_info(account_info_iter)?;
+        if proposal_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidProposalAccountOwner.into());
+        }
+        let mut proposal_acct_data =
+            Proposal::unpack(&proposal_acct.data.borrow()).map_err(Self::proposal_account_error)?;
+        Self::check_open_proposal(&proposal_acct_data, state_acct.key)?;
+
+        // Record the approval, once per member
+        if proposal_acct_data.approvals.contains(member_acct.key) {
+            return Err(RoyaltyDistributorError::ProposalAlreadyApproved.into());
+        }
+        if proposal_acct_data.approvals.len() >= proposal_acct_data.shares.len() {
+            return Err(RoyaltyDistributorError::ProposalAccountTooSmall.into());
+        }
+        proposal_acct_data.approvals.push(*member_acct.key);
+        Self::emit_proposal_change(proposal_acct.key, &proposal_acct_data)?;
+
+        Proposal::pack(proposal_acct_data, &mut proposal_acct.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_execute_proposal(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        if state_acct_data.approval_threshold == 0 {
+            return Err(RoyaltyDistributorError::NotGovernedByMembers.into());
+        }
+        let pda = Pubkey::create_program_address(
+            &[
+                AUTHORITY_SEED,
+                state_acct.key.as_ref(),
+                &[state_acct_data.bump_seed],
+            ],
+            program_id,
+        )?;
+
+        // [Account 1] Proposal account
+        // Should be an open proposal of this distributor
+        let proposal_acct = next_account_info(account_info_iter)?;
+        if proposal_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidProposalAccountOwner.into());
+        }
+        let mut proposal_acct_data =
+            Proposal::unpack(&proposal_acct.data.borrow()).map_err(Self::proposal_account_error)?;
+        Self::check_open_proposal(&proposal_acct_data, state_acct.key)?;
+
+        // Approvals, weighted by current shares, should reach the threshold
+        let approved_shares: u32 = state_acct_data
+            .members
+            .iter()
+            .filter(|member| proposal_acct_data.approvals.contains(&member.pubkey))
+            .map(|member| member.shares as u32)
+            .sum();
+        if approved_shares < state_acct_data.approval_threshold as u32 {
+            return Err(RoyaltyDistributorError::ApprovalThresholdNotReached.into());
+        }
+
+        // [Account 2] Shared account
+        // Should be the shared account of this distributor, owned by its PDA
+        let shared_acct = next_account_info(account_info_iter)?;
+        let shared_balance = Self::shared_balance(shared_acct, &state_acct_data, &pda, program_id)?;
+
+        // [Accounts 3..] Ledger account and shared account of each registered mint
+        let mint_accts = account_info_iter.as_slice();
+
+        // Allocate the shared account balances under the old share table
+        let now = Clock::get()?.unix_timestamp;
+        state_acct_data.settle(shared_balance, now)?;
+        let ledgers = Self::settle_registered_mints(
+            mint_accts,
+            0,
+            state_acct.key,
+            &state_acct_data,
+            &pda,
+            program_id,
+        )?;
+
+        // Rewrite the share table, once the timelock elapses
+        Self::queue_shares(&mut state_acct_data, proposal_acct_data.shares.clone(), now)?;
+        Self::emit_config_change(state_acct.key, &state_acct_data)?;
+        proposal_acct_data.is_executed = true;
+
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+        Proposal::pack(proposal_acct_data, &mut proposal_acct.data.borrow_mut())?;
+        Self::pack_mint_ledgers(ledgers)?;
+
+        Ok(())
+    }
+
+    fn process_propose_admin(
+        accounts: &[AccountInfo],
+        pending_admin: Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Admin account
+        let admin_acct = next_account_info(account_info_iter)?;
+        if !admin_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        Self::check_admin(&state_acct_data, admin_acct)?;
+
+        // The proposed admin becomes admin once it accepts
+        state_acct_data.pending_admin = pending_admin;
+        Self::emit_config_change(state_acct.key, &state_acct_data)?;
+
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_accept_admin(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Pending admin account
+        let pending_admin_acct = next_account_info(account_info_iter)?;
+        if !pending_admin_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        if state_acct_data.pending_admin == Pubkey::default()
+            || state_acct_data.pending_admin != *pending_admin_acct.key
+        {
+            return Err(RoyaltyDistributorError::InvalidPendingAdmin.into());
+        }
+
+        state_acct_data.admin = state_acct_data.pending_admin;
+        state_acct_data.pending_admin = Pubkey::default();
+        Self::emit_config_change(state_acct.key, &state_acct_data)?;
+
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_renounce_admin(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Admin account
+        let admin_acct = next_account_info(account_info_iter)?;
+        if !admin_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        Self::check_admin(&state_acct_data, admin_acct)?;
+
+        // [Accounts 2..] Members approving the end of member governance, if any
+        // Members holding at least the approval threshold should consent to it
+        if state_acct_data.approval_threshold > 0 {
+            Self::check_authorizers(&state_acct_data, account_info_iter.as_slice())?;
+        }
+
+        // Nobody can change the share table anymore
+        state_acct_data.admin = Pubkey::default();
+        state_acct_data.pending_admin = Pubkey::default();
+        state_acct_data.approval_threshold = 0;
+        Self::emit_config_change(state_acct.key, &state_acct_data)?;
+
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_close_distributor(
+        accounts: &[AccountInfo],
+        new_shared_account_owner: Pubkey,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        if state_acct_data.is_immutable() {
+            return Err(RoyaltyDistributorError::DistributorImmutable.into());
+        }
+        // The parent would keep paying into a closed distributor
+        if state_acct_data.is_nested {
+            return Err(RoyaltyDistributorError::DistributorNested.into());
+        }
+        let authority_seeds: &[&[u8]] = &[
+            AUTHORITY_SEED,
+            state_acct.key.as_ref(),
+            &[state_acct_data.bump_seed],
+        ];
+        let pda = Pubkey::create_program_address(authority_seeds, program_id)?;
+
+        // [Account 1] Shared account
+        // Should be the shared account of this distributor, owned by its PDA
+        let shared_acct = next_account_info(account_info_iter)?;
+        let shared_balance = Self::shared_balance(shared_acct, &state_acct_data, &pda, program_id)?;
+
+        // [Account 2] Token program account
+        let token_program_acct = next_account_info(account_info_iter)?;
+        if *token_program_acct.key != spl_token::id() {
+            return Err(RoyaltyDistributorError::InvalidTokenProgram.into());
+        }
+
+        // [Account 3] The PDA account
+        // Should be the PDA of this distributor
+        let pda_acct = next_account_info(account_info_iter)?;
+        if *pda_acct.key != pda {
+            return Err(RoyaltyDistributorError::InvalidAuthority.into());
+        }
+
+        // [Account 4] Recipient of the state account lamports
+        let recipient_acct = next_account_info(account_info_iter)?;
+
+        // [Accounts 5..] Destination token accounts, one per member in member order
+        // Should hold the distributor mint and be owned by the corresponding member,
+        // or be the member wallet for native distributors
+        let remaining_accts = account_info_iter.as_slice();
+        if remaining_accts.len() < state_acct_data.members.len() {
+            return Err(RoyaltyDistributorError::NotEnoughAccounts.into());
+        }
+        let (destination_accts, remaining_accts) =
+            remaining_accts.split_at(state_acct_data.members.len());
+        for (member, destination_acct) in state_acct_data.members.iter().zip(destination_accts) {
+            Self::check_destination_account(
+                destination_acct,
+                &member.payee(program_id),
+                &state_acct_data,
+            )?;
+        }
+
+        // [Accounts after the destinations] State account of each nested distributor
+        // in member order, whose shared account should be the destination of that member
+        // Their nested flags are cleared once the distributor is closed
+        let nested_count = state_acct_data.nested_member_count();
+        if remaining_accts.len() < nested_count {
+            return Err(RoyaltyDistributorError::NotEnoughAccounts.into());
+        }
+        let (nested_accts, remaining_accts) = remaining_accts.split_at(nested_count);
+        Self::check_nested_destinations(
+            &state_acct_data.members,
+            destination_accts,
+            nested_accts,
+            None,
+            program_id,
+        )?;
+
+        // [Accounts after the nested distributors] Ledger account, shared account, destination
+        // token accounts, then the ledger account of each nested distributor, of each
+        // registered mint
+        let mint_accts_len =
+            state_acct_data.mint_count as usize * (2 + destination_accts.len() + nested_count);
+        if remaining_accts.len() < mint_accts_len {
+            return Err(RoyaltyDistributorError::NotEnoughAccounts.into());
+        }
+        let (mint_accts, signer_accts) = remaining_accts.split_at(mint_accts_len);
+
+        // [Remaining accounts] Accounts authorizing the close
+        Self::check_authorizers(&state_acct_data, signer_accts)?;
+
+        // Final distribution: allocate the shared account balances and release
+        // the pending amount of each member
+        // A share table still pending should take effect, or be contested, first
+        state_acct_data.settle(shared_balance, Clock::get()?.unix_timestamp)?;
+        if state_acct_data.has_pending_shares() {
+            return Err(RoyaltyDistributorError::PendingSharesUpdate.into());
+        }
+        let mut ledgers = Self::settle_registered_mints(
+            mint_accts,
+            destination_accts.len() + nested_count,
+            state_acct.key,
+            &state_acct_data,
+            &pda,
+            program_id,
+        )?;
+
+        msg!("Calling the token program to execute the final distribution ...");
+        let mut member_amounts = Vec::with_capacity(destination_accts.len());
+        for (member_index, destination_acct) in destination_accts.iter().enumerate() {
+            let member_amount = state_acct_data.release(member_index)?;
+            member_amounts.push(member_amount);
+            if member_amount == 0 {
+                continue;
+            }
+
+            Self::transfer_from_shared(
+                state_acct_data.is_native,
+                token_program_acct,
+                shared_acct,
+                destination_acct,
+                pda_acct,
+                authority_seeds,
+                member_amount,
+            )?;
+        }
+        Self::emit_distribution(
+            state_acct.key,
+            &state_acct_data.mint,
+            shared_balance,
+            member_amounts,
+        )?;
+
+        if state_acct_data.is_native {
+            // Empty the vault: its rent-exempt minimum and any carried forward
+            // remainder go to the recipient along with the state account lamports
+            Self::refund_lamports(shared_acct, recipient_acct)?;
+        } else {
+            Self::hand_over_shared_account(
+                token_program_acct,
+                shared_acct,
+                pda_acct,
+                authority_seeds,
+                &new_shared_account_owner,
+            )?;
+        }
+
+        // Same for every registered mint, whose ledger account is then zeroed and refunded
+        for (ledger, accts) in ledgers.iter_mut() {
+            let (ledger_acct, shared_acct) = (&accts[0], &accts[1]);
+            let shared_balance = ledger
+                .total_received
+                .checked_sub(ledger.total_released)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+            let (mint_destination_accts, nested_ledger_accts) =
+                accts[2..].split_at(destination_accts.len());
+            Self::check_nested_destinations(
+                &state_acct_data.members,
+                mint_destination_accts,
+                nested_ledger_accts,
+                Some(&ledger.mint),
+                program_id,
+            )?;
+            let mut member_amounts = Vec::with_capacity(destination_accts.len());
+            for (member_index, destination_acct) in mint_destination_accts.iter().enumerate() {
+                Self::check_destination_token_account(
+                    destination_acct,
+                    &state_acct_data.members[member_index].payee(program_id),
+                    &ledger.mint,
+                )?;
+                let member_amount = ledger.release(member_index)?;
+                member_amounts.push(member_amount);
+                if member_amount == 0 {
+                    continue;
+                }
+
+                Self::transfer_from_shared(
+                    false,
+                    token_program_acct,
+                    shared_acct,
+                    destination_acct,
+                    pda_acct,
+                    authority_seeds,
+                    member_amount,
+                )?;
+            }
+            Self::emit_distribution(state_acct.key, &ledger.mint, shared_balance, member_amounts)?;
+
+            Self::hand_over_shared_account(
+                token_program_acct,
+                shared_acct,
+                pda_acct,
+                authority_seeds,
+                &new_shared_account_owner,
+            )?;
+
+            for byte in ledger_acct.data.borrow_mut().iter_mut() {
+                *byte = 0;
+            }
+            Self::refund_lamports(ledger_acct, recipient_acct)?;
+        }
+
+        for nested_acct in nested_accts {
+            Self::unflag_nested_distributor(nested_acct)?;
+        }
+
+        Event::Close {
+            distributor: *state_acct.key,
+            new_shared_account_owner,
+            slot: Clock::get()?.slot,
+        }
+        .emit();
+
+        // Zero the state account and refund its lamports
+        for byte in state_acct.data.borrow_mut().iter_mut() {
+            *byte = 0;
+        }
+        Self::refund_lamports(state_acct, recipient_acct)
+    }
+
+    /// Hands the shared account over to `new_shared_account_owner`, signed by the PDA
+    fn hand_over_shared_account<'a>(
+        token_program_acct: &AccountInfo<'a>,
+        shared_acct: &AccountInfo<'a>,
+        pda_acct: &AccountInfo<'a>,
+        authority_seeds: &[&[u8]],
+        new_shared_account_owner: &Pubkey,
+    ) -> ProgramResult {
+        if *new_shared_account_owner == Pubkey::default() {
+            return Err(RoyaltyDistributorError::InvalidNewSharedAccountOwner.into());
+        }
+        let owner_change_ix = spl_token::instruction::set_authority(
+            token_program_acct.key,         // token program id
+            shared_acct.key,                // account whose authority we would like to change
+            Some(new_shared_account_owner), // new authority of the account
+            AccountOwner,                   // type of authority change
+            pda_acct.key,                   // current account owner
+            &[],                            // signer accounts
+        )?;
+
+        msg!("Calling the token program to transfer shared account ownership ...");
+        invoke_signed(
+            &owner_change_ix,
+            &[
+                shared_acct.clone(),
+                pda_acct.clone(),
+                token_program_acct.clone(),
+            ],
+            &[authority_seeds],
+        )
+    }
+
+    /// Moves every lamport of `acct`, owned by this program, to `recipient_acct`
+    fn refund_lamports(acct: &AccountInfo, recipient_acct: &AccountInfo) -> ProgramResult {
+        let lamports = acct.lamports();
+        **acct.lamports.borrow_mut() = 0;
+        **recipient_acct.lamports.borrow_mut() = recipient_acct
+            .lamports()
+            .checked_add(lamports)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    fn process_contest_shares_update(
+        accounts: &[AccountInfo],
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Account of the member contesting
+        let member_acct = next_account_info(account_info_iter)?;
+        if !member_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+
+        // The pending share table should not have taken effect yet
+        if !state_acct_data.has_pending_shares() {
+            return Err(RoyaltyDistributorError::NoPendingSharesUpdate.into());
+        }
+        if Clock::get()?.unix_timestamp >= state_acct_data.pending_effective_at {
+            return Err(RoyaltyDistributorError::SharesUpdateInEffect.into());
+        }
+
+        // The signer should be a member, contesting once
+        let member = state_acct_data
+            .members
+            .iter_mut()
+            .find(|member| member.pubkey == *member_acct.key)
+            .ok_or(RoyaltyDistributorError::NotAMember)?;
+        if member.contested {
+            return Err(RoyaltyDistributorError::SharesUpdateAlreadyContested.into());
+        }
+        member.contested = true;
+
+        // Members holding more than half of the current shares cancel the change
+        let contested_shares: u32 = state_acct_data
+            .members
+            .iter()
+            .filter(|member| member.contested)
+            .map(|member| member.shares as u32)
+            .sum();
+        if contested_shares * 2 > TOTAL_SHARES as u32 {
+            msg!("Pending share table cancelled");
+            state_acct_data.clear_pending_shares();
+        }
+        Self::emit_config_change(state_acct.key, &state_acct_data)?;
+
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_register_mint(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Owner of the shared account to register
+        // Funds the ledger account
+        let owner_acct = next_account_info(account_info_iter)?;
+        if !owner_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] State account
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+
+        // Extract data from state account
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+        if state_acct_data.is_immutable() {
+            return Err(RoyaltyDistributorError::DistributorImmutable.into());
+        }
+        // Share changes should take effect on every mint at once: a pending share table
+        // should be applied by a withdraw, or contested, first
+        if state_acct_data.has_pending_shares() {
+            return Err(RoyaltyDistributorError::PendingSharesUpdate.into());
+        }
+        let (pda, _) = find_authority_address(state_acct.key, program_id);
+
+        // [Account 2] Shared account of the mint to register
+        // Should be internally owned by token program
+        let shared_acct = next_account_info(account_info_iter)?;
+        if *shared_acct.owner != spl_token::id() {
+            return Err(RoyaltyDistributorError::SharedAccountNotTokenAccount.into());
+        }
+        let shared_acct_data = TokenAccount::unpack(&shared_acct.data.borrow())
+            .map_err(|_| RoyaltyDistributorError::SharedAccountNotTokenAccount)?;
+        if shared_acct_data.mint == state_acct_data.mint {
+            return Err(RoyaltyDistributorError::MintAlreadyRegistered.into());
+        }
+        Self::check_shared_account_authorities(&shared_acct_data, &pda)?;
+
+        // [Account 3] Ledger account of the mint to register
+        // Should be the PDA derived from the state account key and the mint, created below
+        let ledger_acct = next_account_info(account_info_iter)?;
+        let (ledger_key, ledger_bump_seed) =
+            find_mint_ledger_address(state_acct.key, &shared_acct_data.mint, program_id);
+        if *ledger_acct.key != ledger_key {
+            return Err(RoyaltyDistributorError::MintLedgerMismatch.into());
+        }
+        // Anyone may fund the ledger address beforehand: only a ledger already owned
+        // by this program means the mint is registered
+        if ledger_acct.owner == program_id {
+            return Err(RoyaltyDistributorError::MintAlreadyRegistered.into());
+        }
+
+        // [Account 4] Token program account
+        let token_program_acct = next_account_info(account_info_iter)?;
+        if *token_program_acct.key != spl_token::id() {
+            return Err(RoyaltyDistributorError::InvalidTokenProgram.into());
+        }
+
+        // [Account 5] System program account
+        let system_program_acct = next_account_info(account_info_iter)?;
+        if *system_program_acct.key != system_program::id() {
+            return Err(RoyaltyDistributorError::InvalidSystemProgram.into());
+        }
+
+        // [Accounts 6..] Accounts authorizing the registration
+        Self::check_authorizers(&state_acct_data, account_info_iter.as_slice())?;
+
+        // Create the ledger, with room for as many members as the state account
+        msg!("Calling the system program to create the ledger ...");
+        Self::create_pda_account(
+            owner_acct,
+            ledger_acct,
+            system_program_acct,
+            MintLedger::get_packed_len(RoyaltyDistributor::member_capacity(state_acct.data_len())),
+            &[
+                MINT_LEDGER_SEED,
+                state_acct.key.as_ref(),
+                shared_acct_data.mint.as_ref(),
+                &[ledger_bump_seed],
+            ],
+            program_id,
+        )?;
+
+        let ledger = MintLedger {
+            is_initialized: true,
+            bump_seed: ledger_bump_seed,
+            distributor: *state_acct.key,
+            shared_account: *shared_acct.key,
+            mint: shared_acct_data.mint,
+            total_received: 0,
+            total_released: 0,
+            total_distributed: 0,
+            distribution_count: 0,
+            last_distribution_slot: 0,
+            last_distribution_at: 0,
+            entries: vec![LedgerEntry::default(); state_acct_data.members.len()],
+        };
+        Event::MintRegistration {
+            distributor: *state_acct.key,
+            mint: ledger.mint,
+            shared_account: ledger.shared_account,
+            slot: Clock::get()?.slot,
+        }
+        .emit();
+        MintLedger::pack(ledger, &mut ledger_acct.data.borrow_mut())?;
+
+        state_acct_data.mint_count = state_acct_data
+            .mint_count
+            .checked_add(1)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+
+        // Create the 'change owner' instruction
+        let owner_change_ix = spl_token::instruction::set_authority(
+            token_program_acct.key, // token program id
+            shared_acct.key,        // account whose authority we would like to change
+            Some(&pda),             // account that should be the new authority of the account
+            AccountOwner,           // type of authority change
+            owner_acct.key,         // current account owner
+            &[owner_acct.key],      // public keys signing the cross program invocation (CPI)
+        )?;
+
+        msg!("Calling the token program to transfer shared account ownership ...");
+        invoke(
+            &owner_change_ix,
+            &[
+                shared_acct.clone(),
+                owner_acct.clone(),
+                token_program_acct.clone(),
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    fn process_nest_distributor(
+        accounts: &[AccountInfo],
+        member_index: u16,
+        program_id: &Pubkey,
+    ) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] State account of the parent distributor
+        // Should be owned by this program
+        let state_acct = next_account_info(account_info_iter)?;
+        if state_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+        let mut state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+
+        // [Account 1] State account of the distributor to nest
+        // Should be owned by this program and be the member at `member_index`
+        let nested_acct = next_account_info(account_info_iter)?;
+        if nested_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+        }
+        if nested_acct.key == state_acct.key {
+            return Err(RoyaltyDistributorError::NestedDistributorMismatch.into());
+        }
+        let mut nested_acct_data = RoyaltyDistributor::unpack(&nested_acct.data.borrow())
+            .map_err(Self::state_account_error)?;
+
+        let member = state_acct_data
+            .members
+            .get(member_index as usize)
+            .ok_or(RoyaltyDistributorError::NotAMember)?;
+        if member.pubkey != *nested_acct.key {
+            return Err(RoyaltyDistributorError::NestedDistributorMismatch.into());
+        }
+        if member.is_distributor {
+            return Err(RoyaltyDistributorError::MemberIsDistributor.into());
+        }
+        // A distributor has at most one parent, and should consent to being nested
+        if nested_acct_data.is_nested {
+            return Err(RoyaltyDistributorError::DistributorNested.into());
+        }
+        if nested_acct_data.is_immutable() {
+            return Err(RoyaltyDistributorError::DistributorImmutable.into());
+        }
+
+        // A nested distributor cannot nest distributors of its own: since edges are only
+        // added below distributors without a parent, nesting can never close a cycle
+        if state_acct_data.is_nested {
+            return Err(RoyaltyDistributorError::NestingCycle.into());
+        }
+        if nested_acct_data.nesting_depth >= MAX_NESTING_DEPTH {
+            return Err(RoyaltyDistributorError::NestingTooDeep.into());
+        }
+
+        // Payouts should fit in the shared account of the nested distributor
+        if nested_acct_data.is_native != state_acct_data.is_native
+            || nested_acct_data.mint != state_acct_data.mint
+        {
+            return Err(RoyaltyDistributorError::MintMismatch.into());
+        }
+
+        // [Accounts 2..] Accounts authorizing the nesting, for both distributors
+        let signer_accts = account_info_iter.as_slice();
+        Self::check_authorizers(&state_acct_data, signer_accts)?;
+        Self::check_authorizers(&nested_acct_data, signer_accts)?;
+
+        state_acct_data.members[member_index as usize].is_distributor = true;
+        state_acct_data.nesting_depth = state_acct_data
+            .nesting_depth
+            .max(nested_acct_data.nesting_depth + 1);
+        nested_acct_data.is_nested = true;
+        Self::emit_config_change(state_acct.key, &state_acct_data)?;
+
+        RoyaltyDistributor::pack(state_acct_data, &mut state_acct.data.borrow_mut())?;
+        RoyaltyDistributor::pack(nested_acct_data, &mut nested_acct.data.borrow_mut())?;
+
+        Ok(())
+    }
+
+    fn process_distribute_tree(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Account executing the distribution
+        // Should be allowed by the distribution policy of every distributor
+        let init_acct = next_account_info(account_info_iter)?;
+
+        // [Account 1] Token program account
+        let token_program_acct = next_account_info(account_info_iter)?;
+
+        // [Accounts 2..] For each distributor, the root first and every nested distributor
+        // after its parent: state account, shared account, PDA account, destination accounts
+        let mut distributor_accts = account_info_iter.as_slice();
+        let mut nested_keys: Vec<Pubkey> = Vec::new();
+        let mut is_root = true;
+        while !distributor_accts.is_empty() {
+            if distributor_accts.len() < 3 {
+                return Err(RoyaltyDistributorError::NotEnoughAccounts.into());
+            }
+            let state_acct = &distributor_accts[0];
+
+            // Distributors below the root should be nested in a distributor already settled
+            if !is_root {
+                let nested_index = nested_keys
+                    .iter()
+                    .position(|key| key == state_acct.key)
+                    .ok_or(RoyaltyDistributorError::NestedDistributorMismatch)?;
+                nested_keys.swap_remove(nested_index);
+            }
+            is_root = false;
+
+            if state_acct.owner != program_id {
+                return Err(RoyaltyDistributorError::InvalidStateAccountOwner.into());
+            }
+            let state_acct_data = RoyaltyDistributor::unpack(&state_acct.data.borrow())
+                .map_err(Self::state_account_error)?;
+            let nested_count = state_acct_data.nested_member_count();
+            let members = state_acct_data.members;
+
+            // State account of each nested distributor, then receipt account and system
+            // program account, after the destination accounts
+            let receipt_accts_len = match state_acct_data.receipt_policy {
+                ReceiptPolicy::Disabled => 0,
+                ReceiptPolicy::Retain(_) => 2,
+            };
+            let accts_len = 3 + members.len() + nested_count + receipt_accts_len;
+            if distributor_accts.len() < accts_len {
+                return Err(RoyaltyDistributorError::NotEnoughAccounts.into());
+            }
+            let (accts, rest) = distributor_accts.split_at(accts_len);
+
+            // Same accounts as a Withdraw of the distributor mint
+            let mut withdraw_accts = vec![
+                init_acct.clone(),
+                accts[0].clone(),
+                accts[1].clone(),
+                token_program_acct.clone(),
+                accts[2].clone(),
+            ];
+            withdraw_accts.extend_from_slice(&accts[3..]);
+            Self::process_withdraw(&withdraw_accts, None, program_id)?;
+
+            nested_keys.extend(
+                members
+                    .iter()
+                    .filter(|member| member.is_distributor)
+                    .map(|member| member.pubkey),
+            );
+            distributor_accts = rest;
+        }
+
+        Ok(())
+    }
+
+    fn process_close_receipt(accounts: &[AccountInfo], program_id: &Pubkey) -> ProgramResult {
+        // Accounts iterator
+        let account_info_iter = &mut accounts.iter();
+
+        // [Account 0] Payer of the receipt
+        let payer_acct = next_account_info(account_info_iter)?;
+        if !payer_acct.is_signer {
+            return Err(RoyaltyDistributorError::MissingSigner.into());
+        }
+
+        // [Account 1] Receipt account
+        // Should be owned by this program and funded by the payer
+        let receipt_acct = next_account_info(account_info_iter)?;
+        if receipt_acct.owner != program_id {
+            return Err(RoyaltyDistributorError::InvalidReceiptAccountOwner.into());
+        }
+        let receipt_acct_data =
+            Receipt::unpack(&receipt_acct.data.borrow()).map_err(Self::receipt_account_error)?;
+        if receipt_acct_data.payer != *payer_acct.key {
+            return Err(RoyaltyDistributorError::InvalidAuthority.into());
+        }
+
+        // The retention period should have elapsed
+        if Clock::get()?.unix_timestamp < receipt_acct_data.retain_until {
+            return Err(RoyaltyDistributorError::ReceiptRetained.into());
+        }
+
+        // Zero the receipt account and refund its lamports
+        for byte in receipt_acct.data.borrow_mut().iter_mut() {
+            *byte = 0;
+        }
+        Self::refund_lamports(receipt_acct, payer_acct)
+    }
 }
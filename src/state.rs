@@ -1,3 +1,8 @@
+use std::{
+    cmp::{Ordering, Reverse},
+    convert::TryFrom,
+};
+
 use arrayref::{array_mut_ref, array_ref, array_refs, mut_array_refs};
 
 use solana_program::{
@@ -6,28 +11,357 @@ use solana_program::{
     pubkey::Pubkey,
 };
 
+use crate::error::RoyaltyDistributorError;
+
+/// Total shares of a royalty distributor, in basis points
+pub const TOTAL_SHARES: u16 = 10000;
+
+/// Seed prefix of the PDA that owns the shared account of a royalty distributor
+pub const AUTHORITY_SEED: &[u8] = b"royalty_distributor";
+
+/// Finds the PDA that owns the shared account of the royalty distributor
+/// whose state is stored in `state_key`
+pub fn find_authority_address(state_key: &Pubkey, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(&[AUTHORITY_SEED, state_key.as_ref()], program_id)
+}
+
+/// Maximum nesting depth of a royalty distributor: how many levels of nested
+/// distributors may sit below it
+pub const MAX_NESTING_DEPTH: u8 = 4;
+
+/// Seed prefix of the PDA holding the ledger of a mint registered with a royalty distributor
+pub const MINT_LEDGER_SEED: &[u8] = b"mint_ledger";
+
+/// Finds the PDA holding the ledger of `mint` for the royalty distributor
+/// whose state is stored in `state_key`
+pub fn find_mint_ledger_address(
+    state_key: &Pubkey,
+    mint: &Pubkey,
+    program_id: &Pubkey,
+) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[MINT_LEDGER_SEED, state_key.as_ref(), mint.as_ref()],
+        program_id,
+    )
+}
+
+/// Seed prefix of the PDA holding the receipt of a distribution round
+pub const RECEIPT_SEED: &[u8] = b"receipt";
+
+/// Finds the PDA holding the receipt of distribution round `round` of the
+/// royalty distributor whose state is stored in `state_key`
+pub fn find_receipt_address(state_key: &Pubkey, round: u64, program_id: &Pubkey) -> (Pubkey, u8) {
+    Pubkey::find_program_address(
+        &[RECEIPT_SEED, state_key.as_ref(), &round.to_le_bytes()],
+        program_id,
+    )
+}
+
+/// What happens to the indivisible remainder of a distribution
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum RemainderPolicy {
+    /// Leave the remainder in the shared account for the next distribution
+    CarryForward,
+    /// Hand the remainder out one unit at a time to the members
+    /// with the largest fractional parts, ties going to the lowest index
+    LargestRemainder,
+    /// Send the remainder to the member at the given index
+    Member(u16),
+}
+
+impl Sealed for RemainderPolicy {}
+
+impl Pack for RemainderPolicy {
+    const LEN: usize = 3;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, RemainderPolicy::LEN];
+        let (tag, member_index) = array_refs![src, 1, 2];
+
+        Ok(match tag {
+            [0] => RemainderPolicy::CarryForward,
+            [1] => RemainderPolicy::LargestRemainder,
+            [2] => RemainderPolicy::Member(u16::from_le_bytes(*member_index)),
+            _ => return Err(ProgramError::InvalidAccountData),
+        })
+    }
+
+    // Serialization
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, RemainderPolicy::LEN];
+        let (tag_dst, member_index_dst) = mut_array_refs![dst, 1, 2];
+
+        let (tag, member_index) = match self {
+            RemainderPolicy::CarryForward => (0, 0),
+            RemainderPolicy::LargestRemainder => (1, 0),
+            RemainderPolicy::Member(member_index) => (2, *member_index),
+        };
+        tag_dst[0] = tag;
+        *member_index_dst = member_index.to_le_bytes();
+    }
+}
+
+/// Who may trigger a distribution to every member
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum DistributionPolicy {
+    /// Anyone may distribute
+    Permissionless,
+    /// Only members may distribute
+    MembersOnly,
+    /// Only the given cranker may distribute
+    Cranker(Pubkey),
+}
+
+impl Sealed for DistributionPolicy {}
+
+impl Pack for DistributionPolicy {
+    const LEN: usize = 33;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, DistributionPolicy::LEN];
+        let (tag, cranker) = array_refs![src, 1, 32];
+
+        Ok(match tag {
+            [0] => DistributionPolicy::Permissionless,
+            [1] => DistributionPolicy::MembersOnly,
+            [2] => DistributionPolicy::Cranker(Pubkey::new_from_array(*cranker)),
+            _ => return Err(ProgramError::InvalidAccountData),
+        })
+    }
+
+    // Serialization
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, DistributionPolicy::LEN];
+        let (tag_dst, cranker_dst) = mut_array_refs![dst, 1, 32];
+
+        let (tag, cranker) = match self {
+            DistributionPolicy::Permissionless => (0, Pubkey::default()),
+            DistributionPolicy::MembersOnly => (1, Pubkey::default()),
+            DistributionPolicy::Cranker(cranker) => (2, *cranker),
+        };
+        tag_dst[0] = tag;
+        cranker_dst.copy_from_slice(cranker.as_ref());
+    }
+}
+
+/// Whether each withdraw records a receipt of the distribution round
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ReceiptPolicy {
+    /// No receipt
+    Disabled,
+    /// Record a receipt, kept for at least the given retention period, in seconds,
+    /// before its payer may close it and reclaim its rent
+    Retain(i64),
+}
+
+impl Sealed for ReceiptPolicy {}
+
+impl Pack for ReceiptPolicy {
+    const LEN: usize = 9;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, ReceiptPolicy::LEN];
+        let (tag, retention) = array_refs![src, 1, 8];
+
+        Ok(match tag {
+            [0] => ReceiptPolicy::Disabled,
+            [1] => ReceiptPolicy::Retain(i64::from_le_bytes(*retention)),
+            _ => return Err(ProgramError::InvalidAccountData),
+        })
+    }
+
+    // Serialization
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, ReceiptPolicy::LEN];
+        let (tag_dst, retention_dst) = mut_array_refs![dst, 1, 8];
+
+        let (tag, retention) = match self {
+            ReceiptPolicy::Disabled => (0, 0),
+            ReceiptPolicy::Retain(retention) => (1, *retention),
+        };
+        tag_dst[0] = tag;
+        *retention_dst = retention.to_le_bytes();
+    }
+}
+
+pub struct Member {
+    pub pubkey: Pubkey, // 32 bytes
+    pub shares: u16,    // 2 bytes
+
+    // Cumulative amounts allocated to and released to the member: `released`
+    // is what the member earned over its lifetime in the distributor mint
+    pub allocated: u64, // 8 bytes
+    pub released: u64,  // 8 bytes
+
+    // Shares of the member in the pending share table, if any
+    pub pending_shares: u16, // 2 bytes
+
+    // Whether the member contests the pending share table
+    pub contested: bool, // stored as 1 byte
+
+    // Whether the member is another royalty distributor, whose state is stored
+    // in the member public key, and receives payouts in its shared account
+    pub is_distributor: bool, // stored as 1 byte
+}
+
+impl Member {
+    pub fn new(pubkey: Pubkey, shares: u16) -> Self {
+        Member {
+            pubkey,
+            shares,
+            allocated: 0,
+            released: 0,
+            pending_shares: 0,
+            contested: false,
+            is_distributor: false,
+        }
+    }
+
+    /// Amount allocated to the member but not released yet
+    pub fn pending(&self) -> u64 {
+        self.allocated.saturating_sub(self.released)
+    }
+
+    /// Owner of the accounts receiving the payouts of the member: the member itself,
+    /// or the PDA owning the shared account of a nested distributor
+    pub fn payee(&self, program_id: &Pubkey) -> Pubkey {
+        if self.is_distributor {
+            find_authority_address(&self.pubkey, program_id).0
+        } else {
+            self.pubkey
+        }
+    }
+}
+
+impl Sealed for Member {}
+
+impl Pack for Member {
+    const LEN: usize = 54;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, Member::LEN];
+        let (pubkey, shares, allocated, released, pending_shares, contested, is_distributor) =
+            array_refs![src, 32, 2, 8, 8, 2, 1, 1];
+
+        let contested = match contested {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let is_distributor = match is_distributor {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        Ok(Member {
+            pubkey: Pubkey::new_from_array(*pubkey),
+            shares: u16::from_le_bytes(*shares),
+            allocated: u64::from_le_bytes(*allocated),
+            released: u64::from_le_bytes(*released),
+            pending_shares: u16::from_le_bytes(*pending_shares),
+            contested,
+            is_distributor,
+        })
+    }
+
+    // Serialization
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, Member::LEN];
+        let (
+            pubkey_dst,
+            shares_dst,
+            allocated_dst,
+            released_dst,
+            pending_shares_dst,
+            contested_dst,
+            is_distributor_dst,
+        ) = mut_array_refs![dst, 32, 2, 8, 8, 2, 1, 1];
+
+        pubkey_dst.copy_from_slice(self.pubkey.as_ref());
+        *shares_dst = self.shares.to_le_bytes();
+        *allocated_dst = self.allocated.to_le_bytes();
+        *released_dst = self.released.to_le_bytes();
+        *pending_shares_dst = self.pending_shares.to_le_bytes();
+        contested_dst[0] = self.contested as u8;
+        is_distributor_dst[0] = self.is_distributor as u8;
+    }
+}
+
+/// Royalty distributor state
+///
+/// The state account has a variable length: a fixed header followed by
+/// one `Member` entry per member. Use `RoyaltyDistributor::get_packed_len`
+/// to size the account for a given number of members. Accounts cannot be
+/// resized once created, so leave room for any member added later.
 pub struct RoyaltyDistributor {
     pub is_initialized: bool, // stored as 1 byte
 
-    // Member's public keys
-    pub member_1_pubkey: Pubkey, // 32 bytes
-    pub member_2_pubkey: Pubkey, // 32 bytes
-    pub member_3_pubkey: Pubkey, // 32 bytes
-    pub member_4_pubkey: Pubkey, // 32 bytes
-    pub member_5_pubkey: Pubkey, // 32 bytes
-    pub member_6_pubkey: Pubkey, // 32 bytes
-    pub member_7_pubkey: Pubkey, // 32 bytes
-    pub member_8_pubkey: Pubkey, // 32 bytes
-
-    // Shares of members
-    pub member_1_shares: u16, // 2 bytes
-    pub member_2_shares: u16, // 2 bytes
-    pub member_3_shares: u16, // 2 bytes
-    pub member_4_shares: u16, // 2 bytes
-    pub member_5_shares: u16, // 2 bytes
-    pub member_6_shares: u16, // 2 bytes
-    pub member_7_shares: u16, // 2 bytes
-    pub member_8_shares: u16, // 2 bytes
+    // Bump seed of the PDA owning the shared account
+    pub bump_seed: u8, // 1 byte
+
+    // Whether royalties are native SOL, held by the PDA itself as a vault,
+    // rather than SPL tokens held by a shared token account
+    pub is_native: bool, // stored as 1 byte
+
+    // Shared token account governed by the distributor and its mint,
+    // the PDA vault and the default public key for native distributors
+    pub shared_account: Pubkey, // 32 bytes
+    pub mint: Pubkey,           // 32 bytes
+
+    // What happens to the remainder of each distribution
+    pub remainder_policy: RemainderPolicy, // 3 bytes
+
+    // Who may trigger a distribution
+    pub distribution_policy: DistributionPolicy, // 33 bytes
+
+    // Whether each withdraw records a receipt
+    pub receipt_policy: ReceiptPolicy, // 9 bytes
+
+    // Authority allowed to change the share table, default if none
+    pub admin: Pubkey, // 32 bytes
+
+    // Authority proposed by the admin, until it accepts, default if none
+    pub pending_admin: Pubkey, // 32 bytes
+
+    // Shares, in basis points, that should approve a proposal to change the
+    // share table, 0 if share changes are not governed by members
+    pub approval_threshold: u16, // 2 bytes
+
+    // Delay, in seconds, before a share table change takes effect
+    pub timelock: i64, // 8 bytes
+
+    // Unix timestamp at which the pending share table, stored in the
+    // member entries, takes effect, 0 if there is no pending share table
+    pub pending_effective_at: i64, // 8 bytes
+
+    // Cumulative amounts received by the distributor, as of the last
+    // settlement, and released to members
+    pub total_received: u64, // 8 bytes
+    pub total_released: u64, // 8 bytes
+
+    // Lifetime payout history of the shared account, kept when members leave:
+    // amount paid out, number of payouts, and slot and Unix timestamp of the last one
+    pub total_distributed: u64,      // 8 bytes
+    pub distribution_count: u64,     // 8 bytes
+    pub last_distribution_slot: u64, // 8 bytes
+    pub last_distribution_at: i64,   // 8 bytes
+
+    // Number of receipts recorded so far, the round of the next receipt
+    pub receipt_count: u64, // 8 bytes
+
+    // Number of mints registered besides the distributor mint, each with its
+    // own shared account and `MintLedger`
+    pub mint_count: u16, // 2 bytes
+
+    // Whether the distributor is a member of another distributor, in which case
+    // it cannot nest distributors of its own anymore, and how many levels of
+    // nested distributors sit below it
+    pub is_nested: bool,   // stored as 1 byte
+    pub nesting_depth: u8, // 1 byte
+
+    // Members, stored as a u16 count followed by the member entries
+    pub members: Vec<Member>, // 2 + 54 bytes per member
 }
 
 impl IsInitialized for RoyaltyDistributor {
@@ -36,120 +370,1648 @@ impl IsInitialized for RoyaltyDistributor {
     }
 }
 
-impl Pack for RoyaltyDistributor {
-    const LEN: usize = 273;
+impl RoyaltyDistributor {
+    /// Length of the fixed header preceding the member entries
+    pub const HEADER_LEN: usize = 256;
 
-    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
-        let src = array_ref![src, 0, RoyaltyDistributor::LEN];
+    /// Length of a state account holding `member_count` members
+    pub fn get_packed_len(member_count: usize) -> usize {
+        Self::HEADER_LEN + member_count * Member::LEN
+    }
+
+    /// Number of members a state account of `data_len` bytes can hold
+    pub fn member_capacity(data_len: usize) -> usize {
+        data_len.saturating_sub(Self::HEADER_LEN) / Member::LEN
+    }
+
+    /// Number of members that are nested distributors
+    pub fn nested_member_count(&self) -> usize {
+        self.members
+            .iter()
+            .filter(|member| member.is_distributor)
+            .count()
+    }
+
+    /// Whether the share table can never change again: there is no admin
+    /// and share changes are not governed by members
+    pub fn is_immutable(&self) -> bool {
+        self.admin == Pubkey::default() && self.approval_threshold == 0
+    }
+
+    /// Splits `amount` between members according to their shares and the
+    /// remainder policy, returning the amount of each member in member order
+    pub fn split(&self, amount: u64) -> Result<Vec<u64>, ProgramError> {
+        let mut amounts = Vec::with_capacity(self.members.len());
+        let mut remainders = Vec::with_capacity(self.members.len());
+
+        for member in self.members.iter() {
+            let product = (amount as u128)
+                .checked_mul(member.shares as u128)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+            let member_amount = product
+                .checked_div(TOTAL_SHARES as u128)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+
+            amounts.push(
+                u64::try_from(member_amount)
+                    .map_err(|_| RoyaltyDistributorError::ArithmeticOverflow)?,
+            );
+            remainders.push(product % TOTAL_SHARES as u128);
+        }
+
+        let distributed = amounts.iter().try_fold(0u64, |total, member_amount| {
+            total
+                .checked_add(*member_amount)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)
+        })?;
+        let remainder = amount
+            .checked_sub(distributed)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+
+        match self.remainder_policy {
+            RemainderPolicy::CarryForward => {}
+            RemainderPolicy::LargestRemainder => {
+                // Stable sort: members with equal remainders keep their order
+                let mut order: Vec<usize> = (0..self.members.len()).collect();
+                order.sort_by_key(|&i| Reverse(remainders[i]));
+
+                for &i in order.iter().take(remainder as usize) {
+                    amounts[i] += 1;
+                }
+            }
+            RemainderPolicy::Member(member_index) => {
+                let member_amount = amounts
+                    .get_mut(member_index as usize)
+                    .ok_or(RoyaltyDistributorError::InvalidRemainderMember)?;
+                *member_amount = member_amount
+                    .checked_add(remainder)
+                    .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+            }
+        }
+
+        Ok(amounts)
+    }
+
+    /// Whether a share table change is waiting to take effect
+    pub fn has_pending_shares(&self) -> bool {
+        self.pending_effective_at != 0
+    }
+
+    /// Drops the pending share table, if any
+    pub fn clear_pending_shares(&mut self) {
+        self.pending_effective_at = 0;
+        for member in self.members.iter_mut() {
+            member.pending_shares = 0;
+            member.contested = false;
+        }
+    }
+
+    /// Allocates everything received since the last settlement, plus any
+    /// remainder carried forward, to members according to their shares,
+    /// then applies the pending share table if it took effect by `now`.
+    /// `balance` is the current balance of the shared account, which should cover
+    /// the amounts allocated to members and not released yet.
+    pub fn settle(&mut self, balance: u64, now: i64) -> Result<(), ProgramError> {
+        self.total_received = balance
+            .checked_add(self.total_released)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+
+        let allocated = self.members.iter().try_fold(0u64, |total, member| {
+            total
+                .checked_add(member.allocated)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)
+        })?;
+        // Funds owed to members left the shared account outside of this program
+        let unallocated = self
+            .total_received
+            .checked_sub(allocated)
+            .ok_or(RoyaltyDistributorError::SharedBalanceBelowPending)?;
+
+        let member_amounts = self.split(unallocated)?;
+        for (member, member_amount) in self.members.iter_mut().zip(member_amounts) {
+            member.allocated = member
+                .allocated
+                .checked_add(member_amount)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+        }
+
+        // Allocations up to now used the old share table
+        if self.has_pending_shares() && now >= self.pending_effective_at {
+            for member in self.members.iter_mut() {
+                member.shares = member.pending_shares;
+            }
+            self.clear_pending_shares();
+        }
+
+        Ok(())
+    }
+
+    /// Releases the pending amount of the member at `member_index`,
+    /// returning the amount to transfer to the member
+    pub fn release(&mut self, member_index: usize) -> Result<u64, ProgramError> {
+        let member = self
+            .members
+            .get_mut(member_index)
+            .ok_or(RoyaltyDistributorError::NotAMember)?;
+        let amount = member.pending();
+
+        member.released = member.allocated;
+        self.total_released = self
+            .total_released
+            .checked_add(amount)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+
+        Ok(amount)
+    }
+
+    /// Records a payout of `amount` from the shared account at `slot` and `timestamp`
+    pub fn record_distribution(
+        &mut self,
+        amount: u64,
+        slot: u64,
+        timestamp: i64,
+    ) -> Result<(), ProgramError> {
+        self.total_distributed = self
+            .total_distributed
+            .checked_add(amount)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+        self.distribution_count = self
+            .distribution_count
+            .checked_add(1)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+        self.last_distribution_slot = slot;
+        self.last_distribution_at = timestamp;
+
+        Ok(())
+    }
+
+    /// Removes the member at `member_index`, whose pending amount should have
+    /// been released first, and forgets what was received on its behalf.
+    /// Removing the member receiving the remainder carries it forward instead.
+    pub fn remove_member(&mut self, member_index: usize) -> Result<Member, ProgramError> {
+        if member_index >= self.members.len() {
+            return Err(RoyaltyDistributorError::NotAMember.into());
+        }
+
+        // Remainder should still go to the same member
+        if let RemainderPolicy::Member(remainder_index) = self.remainder_policy {
+            match (remainder_index as usize).cmp(&member_index) {
+                Ordering::Less => {}
+                Ordering::Equal => self.remainder_policy = RemainderPolicy::CarryForward,
+                Ordering::Greater => {
+                    self.remainder_policy = RemainderPolicy::Member(remainder_index - 1)
+                }
+            }
+        }
+
+        let member = self.members.remove(member_index);
+        self.total_received = self
+            .total_received
+            .checked_sub(member.allocated)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+        self.total_released = self
+            .total_released
+            .checked_sub(member.released)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+
+        Ok(member)
+    }
+
+    /// Unpack from slice without checking if initialized
+    pub fn unpack_unchecked(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Self::HEADER_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let header = array_ref![src, 0, RoyaltyDistributor::HEADER_LEN];
         let (
             is_initialized,
-            member_1_pubkey,
-            member_2_pubkey,
-            member_3_pubkey,
-            member_4_pubkey,
-            member_5_pubkey,
-            member_6_pubkey,
-            member_7_pubkey,
-            member_8_pubkey,
-            member_1_shares,
-            member_2_shares,
-            member_3_shares,
-            member_4_shares,
-            member_5_shares,
-            member_6_shares,
-            member_7_shares,
-            member_8_shares,
-        ) = array_refs![src, 1, 32, 32, 32, 32, 32, 32, 32, 32, 2, 2, 2, 2, 2, 2, 2, 2];
+            bump_seed,
+            is_native,
+            shared_account,
+            mint,
+            remainder_policy,
+            distribution_policy,
+            receipt_policy,
+            admin,
+            pending_admin,
+            approval_threshold,
+            timelock,
+            pending_effective_at,
+            total_received,
+            total_released,
+            total_distributed,
+            distribution_count,
+            last_distribution_slot,
+            last_distribution_at,
+            receipt_count,
+            mint_count,
+            is_nested,
+            nesting_depth,
+            member_count,
+        ) = array_refs![
+            header,
+            1,
+            1,
+            1,
+            32,
+            32,
+            RemainderPolicy::LEN,
+            DistributionPolicy::LEN,
+            ReceiptPolicy::LEN,
+            32,
+            32,
+            2,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            2,
+            1,
+            1,
+            2
+        ];
 
         let is_initialized = match is_initialized {
             [0] => false,
             [1] => true,
             _ => return Err(ProgramError::InvalidAccountData),
         };
+        let is_native = match is_native {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let is_nested = match is_nested {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let member_count = u16::from_le_bytes(*member_count) as usize;
+        if src.len() < Self::get_packed_len(member_count) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let members = src[Self::HEADER_LEN..Self::get_packed_len(member_count)]
+            .chunks_exact(Member::LEN)
+            .map(Member::unpack_from_slice)
+            .collect::<Result<Vec<_>, _>>()?;
 
         Ok(RoyaltyDistributor {
             is_initialized,
-            member_1_pubkey: Pubkey::new_from_array(*member_1_pubkey),
-            member_2_pubkey: Pubkey::new_from_array(*member_2_pubkey),
-            member_3_pubkey: Pubkey::new_from_array(*member_3_pubkey),
-            member_4_pubkey: Pubkey::new_from_array(*member_4_pubkey),
-            member_5_pubkey: Pubkey::new_from_array(*member_5_pubkey),
-            member_6_pubkey: Pubkey::new_from_array(*member_6_pubkey),
-            member_7_pubkey: Pubkey::new_from_array(*member_7_pubkey),
-            member_8_pubkey: Pubkey::new_from_array(*member_8_pubkey),
-            member_1_shares: u16::from_le_bytes(*member_1_shares),
-            member_2_shares: u16::from_le_bytes(*member_2_shares),
-            member_3_shares: u16::from_le_bytes(*member_3_shares),
-            member_4_shares: u16::from_le_bytes(*member_4_shares),
-            member_5_shares: u16::from_le_bytes(*member_5_shares),
-            member_6_shares: u16::from_le_bytes(*member_6_shares),
-            member_7_shares: u16::from_le_bytes(*member_7_shares),
-            member_8_shares: u16::from_le_bytes(*member_8_shares),
+            bump_seed: bump_seed[0],
+            is_native,
+            shared_account: Pubkey::new_from_array(*shared_account),
+            mint: Pubkey::new_from_array(*mint),
+            remainder_policy: RemainderPolicy::unpack_from_slice(remainder_policy)?,
+            distribution_policy: DistributionPolicy::unpack_from_slice(distribution_policy)?,
+            receipt_policy: ReceiptPolicy::unpack_from_slice(receipt_policy)?,
+            admin: Pubkey::new_from_array(*admin),
+            pending_admin: Pubkey::new_from_array(*pending_admin),
+            approval_threshold: u16::from_le_bytes(*approval_threshold),
+            timelock: i64::from_le_bytes(*timelock),
+            pending_effective_at: i64::from_le_bytes(*pending_effective_at),
+            total_received: u64::from_le_bytes(*total_received),
+            total_released: u64::from_le_bytes(*total_released),
+            total_distributed: u64::from_le_bytes(*total_distributed),
+            distribution_count: u64::from_le_bytes(*distribution_count),
+            last_distribution_slot: u64::from_le_bytes(*last_distribution_slot),
+            last_distribution_at: i64::from_le_bytes(*last_distribution_at),
+            receipt_count: u64::from_le_bytes(*receipt_count),
+            mint_count: u16::from_le_bytes(*mint_count),
+            is_nested,
+            nesting_depth: nesting_depth[0],
+            members,
+        })
+    }
+
+    /// Unpack from slice and check if initialized
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        let value = Self::unpack_unchecked(src)?;
+        if value.is_initialized() {
+            Ok(value)
+        } else {
+            Err(ProgramError::UninitializedAccount)
+        }
+    }
+
+    /// Pack into slice, which should be large enough for all members
+    pub fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() < Self::get_packed_len(src.members.len()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let header = array_mut_ref![dst, 0, RoyaltyDistributor::HEADER_LEN];
+        let (
+            is_initialized_dst,
+            bump_seed_dst,
+            is_native_dst,
+            shared_account_dst,
+            mint_dst,
+            remainder_policy_dst,
+            distribution_policy_dst,
+            receipt_policy_dst,
+            admin_dst,
+            pending_admin_dst,
+            approval_threshold_dst,
+            timelock_dst,
+            pending_effective_at_dst,
+            total_received_dst,
+            total_released_dst,
+            total_distributed_dst,
+            distribution_count_dst,
+            last_distribution_slot_dst,
+            last_distribution_at_dst,
+            receipt_count_dst,
+            mint_count_dst,
+            is_nested_dst,
+            nesting_depth_dst,
+            member_count_dst,
+        ) = mut_array_refs![
+            header,
+            1,
+            1,
+            1,
+            32,
+            32,
+            RemainderPolicy::LEN,
+            DistributionPolicy::LEN,
+            ReceiptPolicy::LEN,
+            32,
+            32,
+            2,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            8,
+            2,
+            1,
+            1,
+            2
+        ];
+
+        is_initialized_dst[0] = src.is_initialized as u8;
+        bump_seed_dst[0] = src.bump_seed;
+        is_native_dst[0] = src.is_native as u8;
+        shared_account_dst.copy_from_slice(src.shared_account.as_ref());
+        mint_dst.copy_from_slice(src.mint.as_ref());
+        src.remainder_policy.pack_into_slice(remainder_policy_dst);
+        src.distribution_policy
+            .pack_into_slice(distribution_policy_dst);
+        src.receipt_policy.pack_into_slice(receipt_policy_dst);
+        admin_dst.copy_from_slice(src.admin.as_ref());
+        pending_admin_dst.copy_from_slice(src.pending_admin.as_ref());
+        *approval_threshold_dst = src.approval_threshold.to_le_bytes();
+        *timelock_dst = src.timelock.to_le_bytes();
+        *pending_effective_at_dst = src.pending_effective_at.to_le_bytes();
+        *total_received_dst = src.total_received.to_le_bytes();
+        *total_released_dst = src.total_released.to_le_bytes();
+        *total_distributed_dst = src.total_distributed.to_le_bytes();
+        *distribution_count_dst = src.distribution_count.to_le_bytes();
+        *last_distribution_slot_dst = src.last_distribution_slot.to_le_bytes();
+        *last_distribution_at_dst = src.last_distribution_at.to_le_bytes();
+        *receipt_count_dst = src.receipt_count.to_le_bytes();
+        *mint_count_dst = src.mint_count.to_le_bytes();
+        is_nested_dst[0] = src.is_nested as u8;
+        nesting_depth_dst[0] = src.nesting_depth;
+        *member_count_dst = (src.members.len() as u16).to_le_bytes();
+
+        for (member, member_dst) in src
+            .members
+            .iter()
+            .zip(dst[Self::HEADER_LEN..].chunks_exact_mut(Member::LEN))
+        {
+            member.pack_into_slice(member_dst);
+        }
+
+        Ok(())
+    }
+}
+
+/// Proposal to change the share table of a royalty distributor
+///
+/// The proposal account has a variable length: a fixed header followed by
+/// the proposed shares and the approvals collected so far. Use
+/// `Proposal::get_packed_len` to size the account for a given number of members.
+pub struct Proposal {
+    pub is_initialized: bool, // stored as 1 byte
+
+    // State account of the royalty distributor
+    pub distributor: Pubkey, // 32 bytes
+
+    // Member who made the proposal
+    pub proposer: Pubkey, // 32 bytes
+
+    // Unix timestamp after which the proposal cannot be approved or executed
+    pub expires_at: i64, // 8 bytes
+
+    pub is_executed: bool, // stored as 1 byte
+
+    // Proposed shares, in member order, stored as a u16 count followed by the shares
+    pub shares: Vec<u16>, // 2 + 2 bytes per member
+
+    // Members who approved, stored as a u16 count followed by their public keys,
+    // with room for as many approvals as there are shares
+    pub approvals: Vec<Pubkey>, // 2 + 32 bytes per member
+}
+
+impl IsInitialized for Proposal {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Proposal {
+    /// Length of the fixed header preceding the proposed shares
+    pub const HEADER_LEN: usize = 76;
+
+    /// Length of a proposal account for a distributor of `member_count` members
+    pub fn get_packed_len(member_count: usize) -> usize {
+        Self::HEADER_LEN + member_count * 2 + 2 + member_count * 32
+    }
+
+    /// Unpack from slice without checking if initialized
+    pub fn unpack_unchecked(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Self::HEADER_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let header = array_ref![src, 0, Proposal::HEADER_LEN];
+        let (is_initialized, distributor, proposer, expires_at, is_executed, member_count) =
+            array_refs![header, 1, 32, 32, 8, 1, 2];
+
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+        let is_executed = match is_executed {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let member_count = u16::from_le_bytes(*member_count) as usize;
+        if src.len() < Self::get_packed_len(member_count) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let (shares, rest) = src[Self::HEADER_LEN..].split_at(member_count * 2);
+        let shares = shares
+            .chunks_exact(2)
+            .map(|shares| u16::from_le_bytes([shares[0], shares[1]]))
+            .collect();
+
+        let (approval_count, rest) = rest.split_at(2);
+        let approval_count = u16::from_le_bytes([approval_count[0], approval_count[1]]) as usize;
+        if approval_count > member_count {
+            return Err(ProgramError::InvalidAccountData);
+        }
+        let approvals = rest[..approval_count * 32]
+            .chunks_exact(32)
+            .map(Pubkey::new)
+            .collect();
+
+        Ok(Proposal {
+            is_initialized,
+            distributor: Pubkey::new_from_array(*distributor),
+            proposer: Pubkey::new_from_array(*proposer),
+            expires_at: i64::from_le_bytes(*expires_at),
+            is_executed,
+            shares,
+            approvals,
+        })
+    }
+
+    /// Unpack from slice and check if initialized
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        let value = Self::unpack_unchecked(src)?;
+        if value.is_initialized() {
+            Ok(value)
+        } else {
+            Err(ProgramError::UninitializedAccount)
+        }
+    }
+
+    /// Pack into slice, which should be large enough for all shares and approvals
+    pub fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        let member_count = src.shares.len();
+        if dst.len() < Self::get_packed_len(member_count) || src.approvals.len() > member_count {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let header = array_mut_ref![dst, 0, Proposal::HEADER_LEN];
+        let (
+            is_initialized_dst,
+            distributor_dst,
+            proposer_dst,
+            expires_at_dst,
+            is_executed_dst,
+            member_count_dst,
+        ) = mut_array_refs![header, 1, 32, 32, 8, 1, 2];
+
+        is_initialized_dst[0] = src.is_initialized as u8;
+        distributor_dst.copy_from_slice(src.distributor.as_ref());
+        proposer_dst.copy_from_slice(src.proposer.as_ref());
+        *expires_at_dst = src.expires_at.to_le_bytes();
+        is_executed_dst[0] = src.is_executed as u8;
+        *member_count_dst = (member_count as u16).to_le_bytes();
+
+        let (shares_dst, rest) = dst[Self::HEADER_LEN..].split_at_mut(member_count * 2);
+        for (shares, shares_dst) in src.shares.iter().zip(shares_dst.chunks_exact_mut(2)) {
+            shares_dst.copy_from_slice(&shares.to_le_bytes());
+        }
+
+        let (approval_count_dst, rest) = rest.split_at_mut(2);
+        approval_count_dst.copy_from_slice(&(src.approvals.len() as u16).to_le_bytes());
+        for (approval, approval_dst) in src.approvals.iter().zip(rest.chunks_exact_mut(32)) {
+            approval_dst.copy_from_slice(approval.as_ref());
+        }
+
+        Ok(())
+    }
+}
+
+/// Amounts of a registered mint allocated to and released to a member,
+/// `released` being what the member earned over its lifetime in that mint
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct LedgerEntry {
+    pub allocated: u64, // 8 bytes
+    pub released: u64,  // 8 bytes
+}
+
+impl LedgerEntry {
+    /// Amount allocated to the member but not released yet
+    pub fn pending(&self) -> u64 {
+        self.allocated.saturating_sub(self.released)
+    }
+}
+
+impl Sealed for LedgerEntry {}
+
+impl Pack for LedgerEntry {
+    const LEN: usize = 16;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, LedgerEntry::LEN];
+        let (allocated, released) = array_refs![src, 8, 8];
+
+        Ok(LedgerEntry {
+            allocated: u64::from_le_bytes(*allocated),
+            released: u64::from_le_bytes(*released),
         })
     }
 
     // Serialization
     fn pack_into_slice(&self, dst: &mut [u8]) {
-        let dst = array_mut_ref![dst, 0, RoyaltyDistributor::LEN];
+        let dst = array_mut_ref![dst, 0, LedgerEntry::LEN];
+        let (allocated_dst, released_dst) = mut_array_refs![dst, 8, 8];
+
+        *allocated_dst = self.allocated.to_le_bytes();
+        *released_dst = self.released.to_le_bytes();
+    }
+}
+
+/// Ledger of a mint registered with a royalty distributor besides its own mint
+///
+/// The share table of the distributor governs every registered mint; the ledger
+/// only tracks what its shared account received and released, per member.
+/// The ledger account has a variable length: a fixed header followed by one
+/// `LedgerEntry` per member, in member order. Use `MintLedger::get_packed_len`
+/// to size the account for a given number of members.
+pub struct MintLedger {
+    pub is_initialized: bool, // stored as 1 byte
+
+    // Bump seed of the PDA holding the ledger
+    pub bump_seed: u8, // 1 byte
+
+    // State account of the royalty distributor
+    pub distributor: Pubkey, // 32 bytes
+
+    // Shared token account of the mint, owned by the distributor PDA
+    pub shared_account: Pubkey, // 32 bytes
+    pub mint: Pubkey,           // 32 bytes
+
+    // Cumulative amounts received by the shared account, as of the last
+    // settlement, and released to members
+    pub total_received: u64, // 8 bytes
+    pub total_released: u64, // 8 bytes
+
+    // Lifetime payout history of the shared account, kept when members leave:
+    // amount paid out, number of payouts, and slot and Unix timestamp of the last one
+    pub total_distributed: u64,      // 8 bytes
+    pub distribution_count: u64,     // 8 bytes
+    pub last_distribution_slot: u64, // 8 bytes
+    pub last_distribution_at: i64,   // 8 bytes
+
+    // Entries, stored as a u16 count followed by one entry per member
+    pub entries: Vec<LedgerEntry>, // 2 + 16 bytes per member
+}
+
+impl IsInitialized for MintLedger {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl MintLedger {
+    /// Length of the fixed header preceding the entries
+    pub const HEADER_LEN: usize = 148;
+
+    /// Length of a ledger account holding `member_count` entries
+    pub fn get_packed_len(member_count: usize) -> usize {
+        Self::HEADER_LEN + member_count * LedgerEntry::LEN
+    }
+
+    /// Allocates everything the shared account received since the last
+    /// settlement, plus any remainder carried forward, to members according
+    /// to the share table of `distributor`.
+    /// `balance` is the current balance of the shared account, which should cover
+    /// the amounts allocated to members and not released yet.
+    pub fn settle(
+        &mut self,
+        distributor: &RoyaltyDistributor,
+        balance: u64,
+    ) -> Result<(), ProgramError> {
+        if self.entries.len() != distributor.members.len() {
+            return Err(RoyaltyDistributorError::MemberCountMismatch.into());
+        }
+
+        self.total_received = balance
+            .checked_add(self.total_released)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+
+        let allocated = self.entries.iter().try_fold(0u64, |total, entry| {
+            total
+                .checked_add(entry.allocated)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)
+        })?;
+        // Funds owed to members left the shared account outside of this program
+        let unallocated = self
+            .total_received
+            .checked_sub(allocated)
+            .ok_or(RoyaltyDistributorError::SharedBalanceBelowPending)?;
+
+        let member_amounts = distributor.split(unallocated)?;
+        for (entry, member_amount) in self.entries.iter_mut().zip(member_amounts) {
+            entry.allocated = entry
+                .allocated
+                .checked_add(member_amount)
+                .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+        }
+
+        Ok(())
+    }
+
+    /// Releases the pending amount of the member at `member_index`,
+    /// returning the amount to transfer to the member
+    pub fn release(&mut self, member_index: usize) -> Result<u64, ProgramError> {
+        let entry = self
+            .entries
+            .get_mut(member_index)
+            .ok_or(RoyaltyDistributorError::NotAMember)?;
+        let amount = entry.pending();
+
+        entry.released = entry.allocated;
+        self.total_released = self
+            .total_released
+            .checked_add(amount)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+
+        Ok(amount)
+    }
+
+    /// Records a payout of `amount` from the shared account at `slot` and `timestamp`
+    pub fn record_distribution(
+        &mut self,
+        amount: u64,
+        slot: u64,
+        timestamp: i64,
+    ) -> Result<(), ProgramError> {
+        self.total_distributed = self
+            .total_distributed
+            .checked_add(amount)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+        self.distribution_count = self
+            .distribution_count
+            .checked_add(1)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+        self.last_distribution_slot = slot;
+        self.last_distribution_at = timestamp;
+
+        Ok(())
+    }
+
+    /// Removes the entry of the member at `member_index`, whose pending amount
+    /// should have been released first, and forgets what was received on its behalf
+    pub fn remove_member(&mut self, member_index: usize) -> Result<(), ProgramError> {
+        if member_index >= self.entries.len() {
+            return Err(RoyaltyDistributorError::NotAMember.into());
+        }
+
+        let entry = self.entries.remove(member_index);
+        self.total_received = self
+            .total_received
+            .checked_sub(entry.allocated)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+        self.total_released = self
+            .total_released
+            .checked_sub(entry.released)
+            .ok_or(RoyaltyDistributorError::ArithmeticOverflow)?;
+
+        Ok(())
+    }
+
+    /// Unpack from slice without checking if initialized
+    pub fn unpack_unchecked(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Self::HEADER_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let header = array_ref![src, 0, MintLedger::HEADER_LEN];
+        let (
+            is_initialized,
+            bump_seed,
+            distributor,
+            shared_account,
+            mint,
+            total_received,
+            total_released,
+            total_distributed,
+            distribution_count,
+            last_distribution_slot,
+            last_distribution_at,
+            member_count,
+        ) = array_refs![header, 1, 1, 32, 32, 32, 8, 8, 8, 8, 8, 8, 2];
+
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let member_count = u16::from_le_bytes(*member_count) as usize;
+        if src.len() < Self::get_packed_len(member_count) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let entries = src[Self::HEADER_LEN..Self::get_packed_len(member_count)]
+            .chunks_exact(LedgerEntry::LEN)
+            .map(LedgerEntry::unpack_from_slice)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(MintLedger {
+            is_initialized,
+            bump_seed: bump_seed[0],
+            distributor: Pubkey::new_from_array(*distributor),
+            shared_account: Pubkey::new_from_array(*shared_account),
+            mint: Pubkey::new_from_array(*mint),
+            total_received: u64::from_le_bytes(*total_received),
+            total_released: u64::from_le_bytes(*total_released),
+            total_distributed: u64::from_le_bytes(*total_distributed),
+            distribution_count: u64::from_le_bytes(*distribution_count),
+            last_distribution_slot: u64::from_le_bytes(*last_distribution_slot),
+            last_distribution_at: i64::from_le_bytes(*last_distribution_at),
+            entries,
+        })
+    }
 
+    /// Unpack from slice and check if initialized
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        let value = Self::unpack_unchecked(src)?;
+        if value.is_initialized() {
+            Ok(value)
+        } else {
+            Err(ProgramError::UninitializedAccount)
+        }
+    }
+
+    /// Pack into slice, which should be large enough for all entries
+    pub fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() < Self::get_packed_len(src.entries.len()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let header = array_mut_ref![dst, 0, MintLedger::HEADER_LEN];
         let (
             is_initialized_dst,
-            member_1_pubkey_dst,
-            member_2_pubkey_dst,
-            member_3_pubkey_dst,
-            member_4_pubkey_dst,
-            member_5_pubkey_dst,
-            member_6_pubkey_dst,
-            member_7_pubkey_dst,
-            member_8_pubkey_dst,
-            member_1_shares_dst,
-            member_2_shares_dst,
-            member_3_shares_dst,
-            member_4_shares_dst,
-            member_5_shares_dst,
-            member_6_shares_dst,
-            member_7_shares_dst,
-            member_8_shares_dst,
-        ) = mut_array_refs![dst, 1, 32, 32, 32, 32, 32, 32, 32, 32, 2, 2, 2, 2, 2, 2, 2, 2];
-
-        let RoyaltyDistributor {
+            bump_seed_dst,
+            distributor_dst,
+            shared_account_dst,
+            mint_dst,
+            total_received_dst,
+            total_released_dst,
+            total_distributed_dst,
+            distribution_count_dst,
+            last_distribution_slot_dst,
+            last_distribution_at_dst,
+            member_count_dst,
+        ) = mut_array_refs![header, 1, 1, 32, 32, 32, 8, 8, 8, 8, 8, 8, 2];
+
+        is_initialized_dst[0] = src.is_initialized as u8;
+        bump_seed_dst[0] = src.bump_seed;
+        distributor_dst.copy_from_slice(src.distributor.as_ref());
+        shared_account_dst.copy_from_slice(src.shared_account.as_ref());
+        mint_dst.copy_from_slice(src.mint.as_ref());
+        *total_received_dst = src.total_received.to_le_bytes();
+        *total_released_dst = src.total_released.to_le_bytes();
+        *total_distributed_dst = src.total_distributed.to_le_bytes();
+        *distribution_count_dst = src.distribution_count.to_le_bytes();
+        *last_distribution_slot_dst = src.last_distribution_slot.to_le_bytes();
+        *last_distribution_at_dst = src.last_distribution_at.to_le_bytes();
+        *member_count_dst = (src.entries.len() as u16).to_le_bytes();
+
+        for (entry, entry_dst) in src
+            .entries
+            .iter()
+            .zip(dst[Self::HEADER_LEN..].chunks_exact_mut(LedgerEntry::LEN))
+        {
+            entry.pack_into_slice(entry_dst);
+        }
+
+        Ok(())
+    }
+}
+
+/// Share table entry of a member and amount paid to the member in a distribution round
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ReceiptEntry {
+    pub pubkey: Pubkey, // 32 bytes
+    pub shares: u16,    // 2 bytes
+    pub amount: u64,    // 8 bytes
+}
+
+impl Sealed for ReceiptEntry {}
+
+impl Pack for ReceiptEntry {
+    const LEN: usize = 42;
+
+    fn unpack_from_slice(src: &[u8]) -> Result<Self, ProgramError> {
+        let src = array_ref![src, 0, ReceiptEntry::LEN];
+        let (pubkey, shares, amount) = array_refs![src, 32, 2, 8];
+
+        Ok(ReceiptEntry {
+            pubkey: Pubkey::new_from_array(*pubkey),
+            shares: u16::from_le_bytes(*shares),
+            amount: u64::from_le_bytes(*amount),
+        })
+    }
+
+    // Serialization
+    fn pack_into_slice(&self, dst: &mut [u8]) {
+        let dst = array_mut_ref![dst, 0, ReceiptEntry::LEN];
+        let (pubkey_dst, shares_dst, amount_dst) = mut_array_refs![dst, 32, 2, 8];
+
+        pubkey_dst.copy_from_slice(self.pubkey.as_ref());
+        *shares_dst = self.shares.to_le_bytes();
+        *amount_dst = self.amount.to_le_bytes();
+    }
+}
+
+/// Receipt of a distribution round of a royalty distributor, recorded by a withdraw
+///
+/// The receipt account never changes once recorded, until its payer closes it after
+/// the retention period. It has a variable length: a fixed header followed by one
+/// `ReceiptEntry` per member, in member order. Use `Receipt::get_packed_len`
+/// to size the account for a given number of members.
+pub struct Receipt {
+    pub is_initialized: bool, // stored as 1 byte
+
+    // Bump seed of the PDA holding the receipt
+    pub bump_seed: u8, // 1 byte
+
+    // State account of the royalty distributor and round of the distribution
+    pub distributor: Pubkey, // 32 bytes
+    pub round: u64,          // 8 bytes
+
+    // Account that funded the receipt, and may reclaim its rent
+    pub payer: Pubkey, // 32 bytes
+
+    // Mint distributed, the default public key for native distributors,
+    // and balance of the shared account before the distribution
+    pub mint: Pubkey, // 32 bytes
+    pub balance: u64, // 8 bytes
+
+    // Unix timestamps of the distribution, and from which the receipt may be closed
+    pub created_at: i64,   // 8 bytes
+    pub retain_until: i64, // 8 bytes
+
+    // Entries, stored as a u16 count followed by one entry per member
+    pub entries: Vec<ReceiptEntry>, // 2 + 42 bytes per member
+}
+
+impl IsInitialized for Receipt {
+    fn is_initialized(&self) -> bool {
+        self.is_initialized
+    }
+}
+
+impl Receipt {
+    /// Length of the fixed header preceding the entries
+    pub const HEADER_LEN: usize = 132;
+
+    /// Length of a receipt account holding `member_count` entries
+    pub fn get_packed_len(member_count: usize) -> usize {
+        Self::HEADER_LEN + member_count * ReceiptEntry::LEN
+    }
+
+    /// Unpack from slice without checking if initialized
+    pub fn unpack_unchecked(src: &[u8]) -> Result<Self, ProgramError> {
+        if src.len() < Self::HEADER_LEN {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let header = array_ref![src, 0, Receipt::HEADER_LEN];
+        let (
+            is_initialized,
+            bump_seed,
+            distributor,
+            round,
+            payer,
+            mint,
+            balance,
+            created_at,
+            retain_until,
+            member_count,
+        ) = array_refs![header, 1, 1, 32, 8, 32, 32, 8, 8, 8, 2];
+
+        let is_initialized = match is_initialized {
+            [0] => false,
+            [1] => true,
+            _ => return Err(ProgramError::InvalidAccountData),
+        };
+
+        let member_count = u16::from_le_bytes(*member_count) as usize;
+        if src.len() < Self::get_packed_len(member_count) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let entries = src[Self::HEADER_LEN..Self::get_packed_len(member_count)]
+            .chunks_exact(ReceiptEntry::LEN)
+            .map(ReceiptEntry::unpack_from_slice)
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Receipt {
             is_initialized,
-            member_1_pubkey,
-            member_2_pubkey,
-            member_3_pubkey,
-            member_4_pubkey,
-            member_5_pubkey,
-            member_6_pubkey,
-            member_7_pubkey,
-            member_8_pubkey,
-            member_1_shares,
-            member_2_shares,
-            member_3_shares,
-            member_4_shares,
-            member_5_shares,
-            member_6_shares,
-            member_7_shares,
-            member_8_shares,
-        } = self;
-
-        is_initialized_dst[0] = *is_initialized as u8;
-        member_1_pubkey_dst.copy_from_slice(member_1_pubkey.as_ref());
-        member_2_pubkey_dst.copy_from_slice(member_2_pubkey.as_ref());
-        member_3_pubkey_dst.copy_from_slice(member_3_pubkey.as_ref());
-        member_4_pubkey_dst.copy_from_slice(member_4_pubkey.as_ref());
-        member_5_pubkey_dst.copy_from_slice(member_5_pubkey.as_ref());
-        member_6_pubkey_dst.copy_from_slice(member_6_pubkey.as_ref());
-        member_7_pubkey_dst.copy_from_slice(member_7_pubkey.as_ref());
-        member_8_pubkey_dst.copy_from_slice(member_8_pubkey.as_ref());
-        *member_1_shares_dst = member_1_shares.to_le_bytes();
-        *member_2_shares_dst = member_2_shares.to_le_bytes();
-        *member_3_shares_dst = member_3_shares.to_le_bytes();
-        *member_4_shares_dst = member_4_shares.to_le_bytes();
-        *member_5_shares_dst = member_5_shares.to_le_bytes();
-        *member_6_shares_dst = member_6_shares.to_le_bytes();
-        *member_7_shares_dst = member_7_shares.to_le_bytes();
-        *member_8_shares_dst = member_8_shares.to_le_bytes();
-    }
-}
-
-impl Sealed for RoyaltyDistributor {}
+            bump_seed: bump_seed[0],
+            distributor: Pubkey::new_from_array(*distributor),
+            round: u64::from_le_bytes(*round),
+            payer: Pubkey::new_from_array(*payer),
+            mint: Pubkey::new_from_array(*mint),
+            balance: u64::from_le_bytes(*balance),
+            created_at: i64::from_le_bytes(*created_at),
+            retain_until: i64::from_le_bytes(*retain_until),
+            entries,
+        })
+    }
+
+    /// Unpack from slice and check if initialized
+    pub fn unpack(src: &[u8]) -> Result<Self, ProgramError> {
+        let value = Self::unpack_unchecked(src)?;
+        if value.is_initialized() {
+            Ok(value)
+        } else {
+            Err(ProgramError::UninitializedAccount)
+        }
+    }
+
+    /// Pack into slice, which should be large enough for all entries
+    pub fn pack(src: Self, dst: &mut [u8]) -> Result<(), ProgramError> {
+        if dst.len() < Self::get_packed_len(src.entries.len()) {
+            return Err(ProgramError::InvalidAccountData);
+        }
+
+        let header = array_mut_ref![dst, 0, Receipt::HEADER_LEN];
+        let (
+            is_initialized_dst,
+            bump_seed_dst,
+            distributor_dst,
+            round_dst,
+            payer_dst,
+            mint_dst,
+            balance_dst,
+            created_at_dst,
+            retain_until_dst,
+            member_count_dst,
+        ) = mut_array_refs![header, 1, 1, 32, 8, 32, 32, 8, 8, 8, 2];
+
+        is_initialized_dst[0] = src.is_initialized as u8;
+        bump_seed_dst[0] = src.bump_seed;
+        distributor_dst.copy_from_slice(src.distributor.as_ref());
+        *round_dst = src.round.to_le_bytes();
+        payer_dst.copy_from_slice(src.payer.as_ref());
+        mint_dst.copy_from_slice(src.mint.as_ref());
+        *balance_dst = src.balance.to_le_bytes();
+        *created_at_dst = src.created_at.to_le_bytes();
+        *retain_until_dst = src.retain_until.to_le_bytes();
+        *member_count_dst = (src.entries.len() as u16).to_le_bytes();
+
+        for (entry, entry_dst) in src
+            .entries
+            .iter()
+            .zip(dst[Self::HEADER_LEN..].chunks_exact_mut(ReceiptEntry::LEN))
+        {
+            entry.pack_into_slice(entry_dst);
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_split_carry_forward() {
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+        distributor.remainder_policy = RemainderPolicy::CarryForward;
+        distributor.members = vec![
+            Member::new(Pubkey::new_unique(), 3333),
+            Member::new(Pubkey::new_unique(), 3333),
+            Member::new(Pubkey::new_unique(), 3334),
+        ];
+
+        assert_eq!(distributor.split(100).unwrap(), vec![33, 33, 33]);
+        assert_eq!(distributor.split(10000).unwrap(), vec![3333, 3333, 3334]);
+        assert_eq!(distributor.split(0).unwrap(), vec![0, 0, 0]);
+    }
+
+    #[test]
+    fn test_split_largest_remainder() {
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+        distributor.remainder_policy = RemainderPolicy::LargestRemainder;
+        distributor.members = vec![
+            Member::new(Pubkey::new_unique(), 3333),
+            Member::new(Pubkey::new_unique(), 3333),
+            Member::new(Pubkey::new_unique(), 3334),
+        ];
+        assert_eq!(distributor.split(100).unwrap(), vec![33, 33, 34]);
+
+        // 1.05, 4.2, 1.75: the remainder goes to the largest fractional parts
+        distributor.members = vec![
+            Member::new(Pubkey::new_unique(), 1500),
+            Member::new(Pubkey::new_unique(), 6000),
+            Member::new(Pubkey::new_unique(), 2500),
+        ];
+        assert_eq!(distributor.split(7).unwrap(), vec![1, 4, 2]);
+    }
+
+    #[test]
+    fn test_split_largest_remainder_ties() {
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+        distributor.remainder_policy = RemainderPolicy::LargestRemainder;
+        distributor.members = (0..4)
+            .map(|_| Member::new(Pubkey::new_unique(), 2500))
+            .collect();
+
+        // Equal fractional parts: the lowest indexes win
+        assert_eq!(distributor.split(1).unwrap(), vec![1, 0, 0, 0]);
+        assert_eq!(distributor.split(3).unwrap(), vec![1, 1, 1, 0]);
+        assert_eq!(distributor.split(6).unwrap(), vec![2, 2, 1, 1]);
+    }
+
+    #[test]
+    fn test_split_member() {
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+        distributor.remainder_policy = RemainderPolicy::Member(1);
+        distributor.members = vec![
+            Member::new(Pubkey::new_unique(), 3333),
+            Member::new(Pubkey::new_unique(), 3333),
+            Member::new(Pubkey::new_unique(), 3334),
+        ];
+        assert_eq!(distributor.split(100).unwrap(), vec![33, 34, 33]);
+
+        distributor.remainder_policy = RemainderPolicy::Member(3);
+        assert_eq!(
+            distributor.split(100),
+            Err(RoyaltyDistributorError::InvalidRemainderMember.into())
+        );
+    }
+
+    #[test]
+    fn test_split_max_amount() {
+        let half = u64::MAX / 2;
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+
+        distributor.members = vec![Member::new(Pubkey::new_unique(), 10000)];
+        assert_eq!(distributor.split(u64::MAX).unwrap(), vec![u64::MAX]);
+
+        distributor.members = vec![
+            Member::new(Pubkey::new_unique(), 5000),
+            Member::new(Pubkey::new_unique(), 5000),
+        ];
+        distributor.remainder_policy = RemainderPolicy::CarryForward;
+        assert_eq!(distributor.split(u64::MAX).unwrap(), vec![half, half]);
+        distributor.remainder_policy = RemainderPolicy::LargestRemainder;
+        assert_eq!(distributor.split(u64::MAX).unwrap(), vec![half + 1, half]);
+        distributor.remainder_policy = RemainderPolicy::Member(1);
+        assert_eq!(distributor.split(u64::MAX).unwrap(), vec![half, half + 1]);
+
+        distributor.members = vec![
+            Member::new(Pubkey::new_unique(), 1),
+            Member::new(Pubkey::new_unique(), 9999),
+        ];
+        distributor.remainder_policy = RemainderPolicy::LargestRemainder;
+        let amounts = distributor.split(u64::MAX).unwrap();
+        assert_eq!(
+            amounts.iter().map(|amount| *amount as u128).sum::<u128>(),
+            u64::MAX as u128
+        );
+    }
+
+    #[test]
+    fn test_settle_carry_forward() {
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+        distributor.remainder_policy = RemainderPolicy::CarryForward;
+        distributor.members = vec![
+            Member::new(Pubkey::new_unique(), 5000),
+            Member::new(Pubkey::new_unique(), 5000),
+        ];
+
+        // The remainder stays unallocated in the shared account...
+        distributor.settle(1, 0).unwrap();
+        assert_eq!(distributor.members[0].allocated, 0);
+        distributor.settle(3, 0).unwrap();
+        assert_eq!(distributor.members[0].allocated, 1);
+        assert_eq!(distributor.members[1].allocated, 1);
+
+        // ...and is split along with what is received next
+        distributor.settle(4, 0).unwrap();
+        assert_eq!(distributor.members[0].allocated, 2);
+        assert_eq!(distributor.members[1].allocated, 2);
+        assert_eq!(distributor.total_received, 4);
+    }
+
+    #[test]
+    fn test_settle_and_release() {
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+        distributor.remainder_policy = RemainderPolicy::CarryForward;
+        distributor.members = vec![
+            Member::new(Pubkey::new_unique(), 7000),
+            Member::new(Pubkey::new_unique(), 3000),
+        ];
+
+        distributor.settle(10, 0).unwrap();
+        assert_eq!(distributor.release(0).unwrap(), 7);
+        assert_eq!(distributor.release(0).unwrap(), 0);
+        assert_eq!(distributor.total_released, 7);
+
+        // 3 left in the shared account, 5 more received
+        distributor.settle(8, 0).unwrap();
+        assert_eq!(distributor.total_received, 15);
+        assert_eq!(distributor.release(1).unwrap(), 4);
+        assert_eq!(distributor.release(0).unwrap(), 3);
+        assert_eq!(distributor.total_released, 14);
+
+        // Only the carried forward remainder is left
+        distributor.settle(1, 0).unwrap();
+        assert_eq!(distributor.members[0].pending(), 0);
+        assert_eq!(distributor.members[1].pending(), 0);
+        assert_eq!(
+            distributor.release(2),
+            Err(RoyaltyDistributorError::NotAMember.into())
+        );
+    }
+
+    #[test]
+    fn test_settle_balance_below_pending() {
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+        distributor.members = vec![Member::new(Pubkey::new_unique(), 10000)];
+
+        // Funds owed to members cannot leave the shared account unnoticed
+        distributor.settle(10, 0).unwrap();
+        assert_eq!(
+            distributor.settle(9, 0),
+            Err(RoyaltyDistributorError::SharedBalanceBelowPending.into())
+        );
+        distributor.release(0).unwrap();
+        distributor.settle(0, 0).unwrap();
+    }
+
+    #[test]
+    fn test_settle_pending_shares() {
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+        distributor.members = vec![
+            Member::new(Pubkey::new_unique(), 7000),
+            Member::new(Pubkey::new_unique(), 3000),
+        ];
+        distributor.members[0].pending_shares = 5000;
+        distributor.members[1].pending_shares = 5000;
+        distributor.pending_effective_at = 100;
+
+        // Before the timelock elapses, the current share table applies
+        distributor.settle(10, 99).unwrap();
+        assert_eq!(distributor.members[0].allocated, 7);
+        assert!(distributor.has_pending_shares());
+
+        // What was received so far is allocated under the old share table,
+        // then the pending one takes effect
+        distributor.settle(20, 100).unwrap();
+        assert_eq!(distributor.members[0].allocated, 14);
+        assert_eq!(distributor.members[0].shares, 5000);
+        assert!(!distributor.has_pending_shares());
+
+        distributor.settle(30, 100).unwrap();
+        assert_eq!(distributor.members[0].allocated, 19);
+        assert_eq!(distributor.members[1].allocated, 11);
+    }
+
+    #[test]
+    fn test_pack_royalty_distributor() {
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+        distributor.is_initialized = true;
+        distributor.bump_seed = 254;
+        distributor.shared_account = Pubkey::new_unique();
+        distributor.mint = Pubkey::new_unique();
+        distributor.remainder_policy = RemainderPolicy::Member(1);
+        distributor.distribution_policy = DistributionPolicy::Cranker(Pubkey::new_unique());
+        distributor.receipt_policy = ReceiptPolicy::Retain(86400);
+        distributor.admin = Pubkey::new_unique();
+        distributor.pending_admin = Pubkey::new_unique();
+        distributor.approval_threshold = 5001;
+        distributor.timelock = 3600;
+        distributor.pending_effective_at = 1_700_000_000;
+        distributor.total_received = 1000;
+        distributor.total_released = 400;
+        distributor.total_distributed = 400;
+        distributor.distribution_count = 3;
+        distributor.last_distribution_slot = 42;
+        distributor.last_distribution_at = 1_600_000_000;
+        distributor.receipt_count = 2;
+        distributor.mint_count = 1;
+        distributor.is_nested = true;
+        distributor.nesting_depth = 2;
+        let mut nested = Member::new(Pubkey::new_unique(), 4000);
+        nested.allocated = 400;
+        nested.released = 400;
+        nested.pending_shares = 5000;
+        nested.contested = true;
+        nested.is_distributor = true;
+        distributor.members = vec![Member::new(Pubkey::new_unique(), 6000), nested];
+
+        let packed_len = RoyaltyDistributor::get_packed_len(2);
+        assert_eq!(packed_len, RoyaltyDistributor::HEADER_LEN + 2 * Member::LEN);
+        let mut packed = vec![0; packed_len];
+        let expected = (
+            distributor.shared_account,
+            distributor.distribution_policy,
+            distributor.admin,
+            distributor.pending_admin,
+            distributor.members[1].pubkey,
+        );
+        RoyaltyDistributor::pack(distributor, &mut packed).unwrap();
+
+        let unpacked = RoyaltyDistributor::unpack(&packed).unwrap();
+        assert_eq!(unpacked.bump_seed, 254);
+        assert_eq!(unpacked.shared_account, expected.0);
+        assert_eq!(unpacked.remainder_policy, RemainderPolicy::Member(1));
+        assert_eq!(unpacked.distribution_policy, expected.1);
+        assert_eq!(unpacked.receipt_policy, ReceiptPolicy::Retain(86400));
+        assert_eq!(unpacked.admin, expected.2);
+        assert_eq!(unpacked.pending_admin, expected.3);
+        assert_eq!(unpacked.approval_threshold, 5001);
+        assert_eq!(unpacked.pending_effective_at, 1_700_000_000);
+        assert_eq!(unpacked.last_distribution_at, 1_600_000_000);
+        assert_eq!(unpacked.mint_count, 1);
+        assert!(unpacked.is_nested);
+        assert_eq!(unpacked.nesting_depth, 2);
+        assert_eq!(unpacked.members.len(), 2);
+        assert_eq!(unpacked.members[1].pubkey, expected.4);
+        assert_eq!(unpacked.members[1].pending_shares, 5000);
+        assert!(unpacked.members[1].contested);
+        assert!(unpacked.members[1].is_distributor);
+
+        // Every field survives the round trip
+        let mut repacked = vec![0; packed_len];
+        RoyaltyDistributor::pack(unpacked, &mut repacked).unwrap();
+        assert_eq!(repacked, packed);
+    }
+
+    #[test]
+    fn test_pack_royalty_distributor_too_small() {
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+        distributor.is_initialized = true;
+        distributor.members = vec![Member::new(Pubkey::new_unique(), 10000)];
+
+        let packed_len = RoyaltyDistributor::get_packed_len(1);
+        let mut packed = vec![0; packed_len - 1];
+        assert_eq!(
+            RoyaltyDistributor::pack(distributor, &mut packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let mut packed = vec![0; packed_len];
+        packed[0] = 1;
+        // Member count of one, with the member entry cut off
+        packed[RoyaltyDistributor::HEADER_LEN - 2] = 1;
+        for len in 0..packed_len {
+            assert_eq!(
+                RoyaltyDistributor::unpack_unchecked(&packed[..len]).err(),
+                Some(ProgramError::InvalidAccountData),
+                "length {}",
+                len
+            );
+        }
+        assert!(RoyaltyDistributor::unpack_unchecked(&packed).is_ok());
+    }
+
+    #[test]
+    fn test_pack_proposal() {
+        let distributor = Pubkey::new_unique();
+        let proposer = Pubkey::new_unique();
+        let approvals = vec![proposer, Pubkey::new_unique()];
+        let proposal = Proposal {
+            is_initialized: true,
+            distributor,
+            proposer,
+            expires_at: 1_700_000_000,
+            is_executed: true,
+            shares: vec![5000, 3000, 2000],
+            approvals: approvals.clone(),
+        };
+
+        let packed_len = Proposal::get_packed_len(3);
+        assert_eq!(packed_len, Proposal::HEADER_LEN + 3 * 2 + 2 + 3 * 32);
+        let mut packed = vec![0; packed_len];
+        Proposal::pack(proposal, &mut packed).unwrap();
+
+        let unpacked = Proposal::unpack(&packed).unwrap();
+        assert_eq!(unpacked.distributor, distributor);
+        assert_eq!(unpacked.proposer, proposer);
+        assert_eq!(unpacked.expires_at, 1_700_000_000);
+        assert!(unpacked.is_executed);
+        assert_eq!(unpacked.shares, vec![5000, 3000, 2000]);
+        assert_eq!(unpacked.approvals, approvals);
+
+        let mut repacked = vec![0; packed_len];
+        Proposal::pack(unpacked, &mut repacked).unwrap();
+        assert_eq!(repacked, packed);
+    }
+
+    #[test]
+    fn test_pack_proposal_too_small() {
+        let proposal = Proposal {
+            is_initialized: true,
+            distributor: Pubkey::new_unique(),
+            proposer: Pubkey::new_unique(),
+            expires_at: 0,
+            is_executed: false,
+            shares: vec![5000, 5000],
+            approvals: vec![],
+        };
+        let packed_len = Proposal::get_packed_len(2);
+        let mut packed = vec![0; packed_len - 1];
+        assert_eq!(
+            Proposal::pack(proposal, &mut packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let mut packed = vec![0; packed_len];
+        packed[0] = 1;
+        // Two proposed shares, with the approvals cut off
+        packed[Proposal::HEADER_LEN - 2] = 2;
+        for len in 0..packed_len {
+            assert_eq!(
+                Proposal::unpack_unchecked(&packed[..len]).err(),
+                Some(ProgramError::InvalidAccountData),
+                "length {}",
+                len
+            );
+        }
+        assert!(Proposal::unpack_unchecked(&packed).is_ok());
+    }
+
+    #[test]
+    fn test_pack_mint_ledger() {
+        let distributor = Pubkey::new_unique();
+        let shared_account = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let entries = vec![
+            LedgerEntry {
+                allocated: 700,
+                released: 500,
+            },
+            LedgerEntry {
+                allocated: 300,
+                released: 0,
+            },
+        ];
+        let ledger = MintLedger {
+            is_initialized: true,
+            bump_seed: 253,
+            distributor,
+            shared_account,
+            mint,
+            total_received: 1000,
+            total_released: 500,
+            total_distributed: 500,
+            distribution_count: 1,
+            last_distribution_slot: 42,
+            last_distribution_at: 1_600_000_000,
+            entries: entries.clone(),
+        };
+
+        let packed_len = MintLedger::get_packed_len(2);
+        assert_eq!(packed_len, MintLedger::HEADER_LEN + 2 * LedgerEntry::LEN);
+        let mut packed = vec![0; packed_len];
+        MintLedger::pack(ledger, &mut packed).unwrap();
+
+        let unpacked = MintLedger::unpack(&packed).unwrap();
+        assert_eq!(unpacked.bump_seed, 253);
+        assert_eq!(unpacked.distributor, distributor);
+        assert_eq!(unpacked.shared_account, shared_account);
+        assert_eq!(unpacked.mint, mint);
+        assert_eq!(unpacked.total_received, 1000);
+        assert_eq!(unpacked.total_released, 500);
+        assert_eq!(unpacked.last_distribution_slot, 42);
+        assert_eq!(unpacked.last_distribution_at, 1_600_000_000);
+        assert_eq!(unpacked.entries, entries);
+
+        let mut repacked = vec![0; packed_len];
+        MintLedger::pack(unpacked, &mut repacked).unwrap();
+        assert_eq!(repacked, packed);
+    }
+
+    #[test]
+    fn test_pack_mint_ledger_too_small() {
+        let ledger = MintLedger {
+            is_initialized: true,
+            bump_seed: 0,
+            distributor: Pubkey::new_unique(),
+            shared_account: Pubkey::new_unique(),
+            mint: Pubkey::new_unique(),
+            total_received: 0,
+            total_released: 0,
+            total_distributed: 0,
+            distribution_count: 0,
+            last_distribution_slot: 0,
+            last_distribution_at: 0,
+            entries: vec![LedgerEntry::default(); 2],
+        };
+        let packed_len = MintLedger::get_packed_len(2);
+        let mut packed = vec![0; packed_len - 1];
+        assert_eq!(
+            MintLedger::pack(ledger, &mut packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let mut packed = vec![0; packed_len];
+        packed[0] = 1;
+        // Two entries, the last one cut off
+        packed[MintLedger::HEADER_LEN - 2] = 2;
+        for len in 0..packed_len {
+            assert_eq!(
+                MintLedger::unpack_unchecked(&packed[..len]).err(),
+                Some(ProgramError::InvalidAccountData),
+                "length {}",
+                len
+            );
+        }
+        assert!(MintLedger::unpack_unchecked(&packed).is_ok());
+    }
+
+    #[test]
+    fn test_pack_receipt() {
+        let distributor = Pubkey::new_unique();
+        let payer = Pubkey::new_unique();
+        let mint = Pubkey::new_unique();
+        let entries = vec![
+            ReceiptEntry {
+                pubkey: Pubkey::new_unique(),
+                shares: 6000,
+                amount: 600,
+            },
+            ReceiptEntry {
+                pubkey: Pubkey::new_unique(),
+                shares: 4000,
+                amount: 400,
+            },
+        ];
+        let receipt = Receipt {
+            is_initialized: true,
+            bump_seed: 252,
+            distributor,
+            round: 7,
+            payer,
+            mint,
+            balance: 1000,
+            created_at: 1_600_000_000,
+            retain_until: 1_600_086_400,
+            entries: entries.clone(),
+        };
+
+        let packed_len = Receipt::get_packed_len(2);
+        assert_eq!(packed_len, Receipt::HEADER_LEN + 2 * ReceiptEntry::LEN);
+        let mut packed = vec![0; packed_len];
+        Receipt::pack(receipt, &mut packed).unwrap();
+
+        let unpacked = Receipt::unpack(&packed).unwrap();
+        assert_eq!(unpacked.bump_seed, 252);
+        assert_eq!(unpacked.distributor, distributor);
+        assert_eq!(unpacked.round, 7);
+        assert_eq!(unpacked.payer, payer);
+        assert_eq!(unpacked.mint, mint);
+        assert_eq!(unpacked.balance, 1000);
+        assert_eq!(unpacked.created_at, 1_600_000_000);
+        assert_eq!(unpacked.retain_until, 1_600_086_400);
+        assert_eq!(unpacked.entries, entries);
+
+        let mut repacked = vec![0; packed_len];
+        Receipt::pack(unpacked, &mut repacked).unwrap();
+        assert_eq!(repacked, packed);
+    }
+
+    #[test]
+    fn test_pack_receipt_too_small() {
+        let receipt = Receipt {
+            is_initialized: true,
+            bump_seed: 0,
+            distributor: Pubkey::new_unique(),
+            round: 0,
+            payer: Pubkey::new_unique(),
+            mint: Pubkey::default(),
+            balance: 0,
+            created_at: 0,
+            retain_until: 0,
+            entries: vec![ReceiptEntry {
+                pubkey: Pubkey::new_unique(),
+                shares: 10000,
+                amount: 0,
+            }],
+        };
+        let packed_len = Receipt::get_packed_len(1);
+        let mut packed = vec![0; packed_len - 1];
+        assert_eq!(
+            Receipt::pack(receipt, &mut packed),
+            Err(ProgramError::InvalidAccountData)
+        );
+
+        let mut packed = vec![0; packed_len];
+        packed[0] = 1;
+        // One entry, cut off
+        packed[Receipt::HEADER_LEN - 2] = 1;
+        for len in 0..packed_len {
+            assert_eq!(
+                Receipt::unpack_unchecked(&packed[..len]).err(),
+                Some(ProgramError::InvalidAccountData),
+                "length {}",
+                len
+            );
+        }
+        assert!(Receipt::unpack_unchecked(&packed).is_ok());
+    }
+
+    #[test]
+    fn test_remove_member_remainder() {
+        let mut distributor =
+            RoyaltyDistributor::unpack_unchecked(&[0; RoyaltyDistributor::HEADER_LEN]).unwrap();
+        distributor.remainder_policy = RemainderPolicy::Member(2);
+        distributor.members = vec![
+            Member::new(Pubkey::new_unique(), 2500),
+            Member::new(Pubkey::new_unique(), 2500),
+            Member::new(Pubkey::new_unique(), 2500),
+            Member::new(Pubkey::new_unique(), 2500),
+        ];
+
+        // Members after the remainder member shift nothing
+        distributor.remove_member(3).unwrap();
+        assert_eq!(distributor.remainder_policy, RemainderPolicy::Member(2));
+
+        // Members before it shift its index
+        distributor.remove_member(0).unwrap();
+        assert_eq!(distributor.remainder_policy, RemainderPolicy::Member(1));
+
+        // Removing it carries the remainder forward
+        distributor.remove_member(1).unwrap();
+        assert_eq!(distributor.remainder_policy, RemainderPolicy::CarryForward);
+        assert_eq!(distributor.members.len(), 1);
+
+        assert_eq!(
+            distributor.remove_member(1).err(),
+            Some(RoyaltyDistributorError::NotAMember.into())
+        );
+    }
+}